@@ -1,10 +1,13 @@
 use std::num::ParseIntError;
 use std::str::FromStr;
 
+#[derive(Clone)]
 struct Rotation(i32);
 
+#[derive(Clone)]
 enum ParseRotationError {
     ParsePrefix,
+    #[allow(dead_code)] // the cause is kept but not yet reported
     ParseInt(ParseIntError),
 }
 
@@ -12,8 +15,10 @@ impl FromStr for Rotation {
     type Err = ParseRotationError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (prefix, num) = s.split_at(1);
-        let count: i32 = num.parse().map_err(|e| ParseRotationError::ParseInt(e))?;
+        let (prefix, num) = s
+            .split_at_checked(1)
+            .ok_or(ParseRotationError::ParsePrefix)?;
+        let count: i32 = num.parse().map_err(ParseRotationError::ParseInt)?;
         let rot = match prefix {
             "L" => -count,
             "R" => count,
@@ -23,9 +28,138 @@ impl FromStr for Rotation {
     }
 }
 
+/// A [Rotation] with an optional `<count>x` prefix, such as `3xL68`, meaning the rotation is
+/// applied that many times in succession.
+struct RepeatedRotation {
+    rot: Rotation,
+    count: usize,
+}
+
+impl FromStr for RepeatedRotation {
+    type Err = ParseRotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((count, rot)) = s.split_once('x') else {
+            return Ok(RepeatedRotation {
+                rot: Rotation::from_str(s)?,
+                count: 1,
+            });
+        };
+        let count: usize = count.parse().map_err(ParseRotationError::ParseInt)?;
+        let rot = Rotation::from_str(rot)?;
+        Ok(RepeatedRotation { rot, count })
+    }
+}
+
+/// Parses rotations in a compact log format, where a bare magnitude such as `30` continues in the
+/// direction of the previous rotation, or R if there is none.
+struct RotationParser {
+    direction: char,
+}
+
+impl RotationParser {
+    fn new() -> Self {
+        RotationParser { direction: 'R' }
+    }
+
+    fn parse(&mut self, token: &str) -> Result<RepeatedRotation, ParseRotationError> {
+        let (count, rot) = match token.split_once('x') {
+            Some((count, rot)) => (Some(count), rot),
+            None => (None, token),
+        };
+        let rot = if rot.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("{}{rot}", self.direction)
+        } else {
+            if let Some(direction @ ('L' | 'R')) = rot.chars().next() {
+                self.direction = direction;
+            }
+            rot.to_string()
+        };
+        match count {
+            Some(count) => RepeatedRotation::from_str(&format!("{count}x{rot}")),
+            None => RepeatedRotation::from_str(&rot),
+        }
+    }
+
+    /// Parses the entry, yielding each repetition of a [RepeatedRotation] as a separate
+    /// [Rotation], or a single error if the entry is malformed.
+    fn expand(&mut self, token: &str) -> std::iter::RepeatN<Result<Rotation, ParseRotationError>> {
+        match self.parse(token) {
+            Ok(RepeatedRotation { rot, count }) => std::iter::repeat_n(Ok(rot), count),
+            Err(e) => std::iter::repeat_n(Err(e), 1),
+        }
+    }
+
+    /// Like [parse_rotations], but continues from the parser's current direction and leaves the
+    /// parser in the direction of the last entry read.
+    fn parse_all<'a>(
+        &'a mut self,
+        r: impl std::io::BufRead + 'a,
+    ) -> impl Iterator<Item = Result<Rotation, ParseRotationError>> + 'a {
+        rotation_entries(r).flat_map(|token| self.expand(&token))
+    }
+}
+
+/// Splits the input into its comma- or newline-separated entries.
+fn rotation_entries(r: impl std::io::BufRead) -> impl Iterator<Item = String> {
+    r.lines().map_while(Result::ok).flat_map(|line| {
+        line.split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>()
+    })
+}
+
+/// Parses the comma- or newline-separated rotations of the input with a [RotationParser], yielding
+/// each repetition of a [RepeatedRotation] as a separate [Rotation], so that repeated rotations are
+/// counted exactly as if written out in full. Each malformed entry yields a single error.
+#[cfg_attr(not(test), allow(dead_code))]
+fn parse_rotations(
+    r: impl std::io::BufRead,
+) -> impl Iterator<Item = Result<Rotation, ParseRotationError>> {
+    let mut parser = RotationParser::new();
+    rotation_entries(r).flat_map(move |token| parser.expand(&token))
+}
+
+/// How the pointer behaves when a rotation carries it past either end of the dial.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum WrapMode {
+    /// Continue from the opposite end.
+    #[default]
+    Wrap,
+    /// Stop at the end.
+    #[cfg_attr(not(test), allow(dead_code))]
+    Clamp,
+    /// Reverse direction at the end, as if reflected.
+    #[cfg_attr(not(test), allow(dead_code))]
+    Bounce,
+}
+
+/// Statistics gathered over a single pass through the input by [Position::summarize].
+#[derive(Debug, Default, PartialEq)]
+struct RunSummary {
+    /// The number of rotations applied, counting each repetition separately.
+    rotations: usize,
+    /// The sum of all rotations applied, before any wrapping.
+    net: i64,
+    exact: usize,
+    passthrough: usize,
+    /// The number of whole turns of the dial, counting `|raw_sum / total_positions|` for each
+    /// rotation, where `raw_sum` is the position before the rotation plus the rotation itself.
+    /// Unlike passthroughs, this does not count arriving at zero from above.
+    revolutions: usize,
+    final_position: i32,
+    /// The number of entries which were skipped because they could not be parsed.
+    skipped: usize,
+}
+
 struct Position {
     current: i32,
     total_positions: i32,
+    wrap_mode: WrapMode,
+    /// The direction of the last rotation read, which a bare magnitude continues in.
+    direction: char,
 }
 
 impl Position {
@@ -33,41 +167,254 @@ impl Position {
         Position {
             current: start,
             total_positions,
+            wrap_mode: WrapMode::default(),
+            direction: RotationParser::new().direction,
         }
     }
 
+    /// Restores a dial from the `(current, total_positions)` pair returned by [Position::state].
+    ///
+    /// The pair is the whole of the dial's own state, since even a bouncing pointer's reflections
+    /// are folded into `current`. The wrap mode is configuration rather than state, so it is
+    /// reapplied with [Position::with_wrap_mode], and if the next chunk may start with a bare
+    /// magnitude, the direction it continues in is reapplied with [Position::with_direction].
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn from_state(current: i32, total_positions: i32) -> Self {
+        Position::new(current, total_positions)
+    }
+
+    /// Returns the `(current, total_positions)` pair, which can be stored as two integers and
+    /// passed back to [Position::from_state] to resume.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn state(&self) -> (i32, i32) {
+        (self.current, self.total_positions)
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn with_wrap_mode(mut self, wrap_mode: WrapMode) -> Self {
+        self.wrap_mode = wrap_mode;
+        self
+    }
+
+    /// Sets the direction in which a bare magnitude continues until a direction is given.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn with_direction(mut self, direction: char) -> Self {
+        self.direction = direction;
+        self
+    }
+
     /// Returns counts for the number of times the rotation results in a final position of 0 (at
     /// most once) and the number of times the rotation passed through zero (including ending
     /// there).
     fn handle_rotation(&mut self, rot: &Rotation) -> (usize, usize) {
+        let passthroughs = match self.wrap_mode {
+            WrapMode::Wrap => self.wrap(rot),
+            WrapMode::Clamp => self.clamp(rot),
+            WrapMode::Bounce => self.bounce(rot),
+        };
+        let exact = if self.current == 0 { 1 } else { 0 };
+        (exact, passthroughs)
+    }
+
+    fn wrap(&mut self, rot: &Rotation) -> usize {
         let raw_sum = self.current + rot.0;
         let mut passthroughs: usize = (raw_sum / self.total_positions).abs().try_into().unwrap();
         if self.current > 0 && raw_sum <= 0 {
             passthroughs += 1;
         }
         self.current = raw_sum.rem_euclid(self.total_positions);
-        let exact = if self.current == 0 { 1 } else { 0 };
-        (exact, passthroughs)
+        passthroughs
+    }
+
+    /// Zero can only be passed through by arriving there from elsewhere, since the pointer cannot
+    /// move beyond it.
+    fn clamp(&mut self, rot: &Rotation) -> usize {
+        let raw_sum = self.current + rot.0;
+        let passthroughs = if self.current > 0 && raw_sum <= 0 {
+            1
+        } else {
+            0
+        };
+        self.current = raw_sum.clamp(0, self.total_positions - 1);
+        passthroughs
+    }
+
+    /// Unfolding the reflections, the pointer moves along an infinite line where every multiple
+    /// of `2 * (total_positions - 1)` corresponds to zero on the dial.
+    fn bounce(&mut self, rot: &Rotation) -> usize {
+        let period = 2 * (self.total_positions - 1);
+        if period == 0 {
+            // A dial with a single position is always at zero, so every step passes through it.
+            return rot.0.unsigned_abs() as usize;
+        }
+        let start = self.current;
+        let end = start + rot.0;
+        let offset = end.rem_euclid(period);
+        self.current = offset.min(period - offset);
+        let passthroughs = if rot.0 >= 0 {
+            // multiples of the period in (start, end]
+            end.div_euclid(period) - start.div_euclid(period)
+        } else {
+            // multiples of the period in [end, start)
+            (start - 1).div_euclid(period) - (end - 1).div_euclid(period)
+        };
+        passthroughs as usize
     }
 
     /// Return the number of times the position lands on zero from the given input.
+    ///
+    /// May be called repeatedly with successive chunks of a larger input, continuing from where
+    /// the previous chunk left the dial, including the direction a bare magnitude at the start of
+    /// a chunk continues in, in which case the counts of each chunk sum to the counts of the
+    /// whole.
+    #[cfg_attr(not(test), allow(dead_code))]
     fn handle_input(&mut self, r: impl std::io::BufRead) -> (usize, usize) {
-        r.lines()
-            .map_while(Result::ok)
-            .filter(|line| !line.is_empty())
-            .map(|line| Rotation::from_str(&line))
-            .filter_map(Result::ok)
-            .fold((0, 0), |acc, rot| {
+        let (exact, passthrough, _) = self.handle_input_with_skipped(r);
+        (exact, passthrough)
+    }
+
+    /// Like [Position::handle_input], but additionally returns the number of entries which were
+    /// skipped because they could not be parsed as a [Rotation].
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn handle_input_with_skipped(&mut self, r: impl std::io::BufRead) -> (usize, usize, usize) {
+        let mut parser = self.parser();
+        let counts = parser.parse_all(r).fold((0, 0, 0), |acc, rot| match rot {
+            Ok(rot) => {
                 let (exact, passthrough) = self.handle_rotation(&rot);
-                (acc.0 + exact, acc.1 + passthrough)
-            })
+                (acc.0 + exact, acc.1 + passthrough, acc.2)
+            }
+            Err(_) => (acc.0, acc.1, acc.2 + 1),
+        });
+        self.direction = parser.direction;
+        counts
+    }
+
+    /// Returns a [RotationParser] which continues in the direction of the last rotation read.
+    fn parser(&self) -> RotationParser {
+        RotationParser {
+            direction: self.direction,
+        }
+    }
+
+    /// Processes the whole input, returning a [RunSummary] of the rotations applied.
+    fn summarize(&mut self, r: impl std::io::BufRead) -> RunSummary {
+        let mut parser = self.parser();
+        let mut summary = parser
+            .parse_all(r)
+            .fold(RunSummary::default(), |mut acc, rot| {
+                match rot {
+                    Ok(rot) => {
+                        let raw_sum = self.current + rot.0;
+                        let (exact, passthrough) = self.handle_rotation(&rot);
+                        acc.rotations += 1;
+                        acc.net += i64::from(rot.0);
+                        acc.exact += exact;
+                        acc.passthrough += passthrough;
+                        acc.revolutions += (raw_sum / self.total_positions).unsigned_abs() as usize;
+                    }
+                    Err(_) => acc.skipped += 1,
+                }
+                acc
+            });
+        self.direction = parser.direction;
+        summary.final_position = self.current;
+        summary
+    }
+
+    /// Returns the single rotation which moves the pointer from its current position to the same
+    /// final position as the whole input would on a wrapping dial, taking the shorter direction and
+    /// preferring R when both are equally short. Only the endpoint is preserved, not the counts of
+    /// zeros passed. Malformed entries are skipped.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn minimal_equivalent(&self, r: impl std::io::BufRead) -> Rotation {
+        let total = i64::from(self.total_positions);
+        let net: i64 = self
+            .parser()
+            .parse_all(r)
+            .filter_map(Result::ok)
+            .map(|rot| i64::from(rot.0))
+            .sum();
+        let forward = net.rem_euclid(total);
+        let rot = if forward <= total / 2 {
+            forward
+        } else {
+            forward - total
+        };
+        Rotation(rot as i32)
+    }
+
+    /// Processes rotations in order until one lands exactly on zero, and returns the 1-based count
+    /// of rotations it took, counting each repetition separately. Malformed entries are skipped and
+    /// not counted. Returns [None] if the input ends without landing on zero.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn rotations_until_zero(&mut self, r: impl std::io::BufRead) -> Option<usize> {
+        let mut parser = self.parser();
+        let count = parser
+            .parse_all(r)
+            .filter_map(Result::ok)
+            .position(|rot| self.handle_rotation(&rot).0 == 1)
+            .map(|index| index + 1);
+        self.direction = parser.direction;
+        count
+    }
+}
+
+/// Returns every start position on a dial of the given size from which replaying the input lands
+/// exactly on zero the target number of times.
+#[cfg_attr(not(test), allow(dead_code))]
+fn starts_with_exact_count(input: &str, total_positions: i32, target: usize) -> Vec<i32> {
+    (0..total_positions)
+        .filter(|start| {
+            let r = std::io::BufReader::new(input.as_bytes());
+            Position::new(*start, total_positions).handle_input(r).0 == target
+        })
+        .collect()
+}
+
+/// Counts the adjacent pairs of rotations which exactly cancel out, such as `R48` followed by
+/// `L48`, skipping any unparseable entries. Pairs may overlap, so `R48,L48,R48` has two, while zero
+/// rotations never count as cancelling.
+#[cfg_attr(not(test), allow(dead_code))]
+fn count_cancellations(r: impl std::io::BufRead) -> usize {
+    let mut prev: Option<Rotation> = None;
+    parse_rotations(r)
+        .filter_map(Result::ok)
+        .filter(|rot| {
+            let cancels = prev.as_ref().is_some_and(|p| p.0 != 0 && p.0 + rot.0 == 0);
+            prev = Some(Rotation(rot.0));
+            cancels
+        })
+        .count()
+}
+
+/// Returns the length of the longest run of consecutive rotations in the same direction, skipping
+/// any unparseable entries. Repeated rotations count once per repetition, and a zero rotation has no
+/// direction, so it breaks any run.
+#[cfg_attr(not(test), allow(dead_code))]
+fn longest_same_direction_run(r: impl std::io::BufRead) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut prev_sign = 0;
+    for rot in parse_rotations(r).filter_map(Result::ok) {
+        let sign = rot.0.signum();
+        current = match sign {
+            0 => 0,
+            _ if sign == prev_sign => current + 1,
+            _ => 1,
+        };
+        prev_sign = sign;
+        longest = longest.max(current);
     }
+    longest
 }
 
 fn main() {
-    let (exact, passthrough) = Position::new(50, 100).handle_input(std::io::stdin().lock());
-    println!("old password: {}", exact);
-    println!("new password: {}", passthrough);
+    let summary = Position::new(50, 100).summarize(std::io::stdin().lock());
+    if summary.skipped > 0 {
+        eprintln!("warning: skipped {} malformed entries", summary.skipped);
+    }
+    println!("old password: {}", summary.exact);
+    println!("new password: {}", summary.passthrough);
 }
 
 #[cfg(test)]
@@ -141,4 +488,383 @@ L82";
         assert_eq!(exact, 3);
         assert_eq!(passthroughs, 6);
     }
+
+    #[test]
+    fn test_resume_from_state() {
+        let lines: Vec<&str> = EXAMPLE_INPUT.lines().collect();
+        for split in 0..=lines.len() {
+            let (first, second) = (lines[..split].join("\n"), lines[split..].join("\n"));
+            let mut position = super::Position::new(50, 100);
+            let counts = position.handle_input(std::io::BufReader::new(first.as_bytes()));
+            let (current, total_positions) = position.state();
+            let mut resumed = super::Position::from_state(current, total_positions);
+            let rest = resumed.handle_input(std::io::BufReader::new(second.as_bytes()));
+            assert_eq!((counts.0 + rest.0, counts.1 + rest.1), (3, 6));
+            assert_eq!(resumed.state(), (32, 100));
+
+            // The same position may also simply be fed both chunks in turn.
+            let mut position = super::Position::new(50, 100);
+            let counts = position.handle_input(std::io::BufReader::new(first.as_bytes()));
+            let rest = position.handle_input(std::io::BufReader::new(second.as_bytes()));
+            assert_eq!((counts.0 + rest.0, counts.1 + rest.1), (3, 6));
+        }
+    }
+
+    #[test]
+    fn test_resume_keeps_wrap_mode_and_direction() {
+        use super::{Position, WrapMode};
+        let entries = [
+            "L68", "30", "R48", "5", "60", "L55", "1", "99", "R14", "L82",
+        ];
+        for wrap_mode in [WrapMode::Wrap, WrapMode::Clamp, WrapMode::Bounce] {
+            let mut whole = Position::new(50, 100).with_wrap_mode(wrap_mode);
+            let expected =
+                whole.handle_input(std::io::BufReader::new(entries.join(",").as_bytes()));
+            for split in 0..=entries.len() {
+                let (first, second) = (entries[..split].join(","), entries[split..].join(","));
+                let mut position = Position::new(50, 100).with_wrap_mode(wrap_mode);
+                let counts = position.handle_input(std::io::BufReader::new(first.as_bytes()));
+                let (current, total_positions) = position.state();
+                let mut resumed = Position::from_state(current, total_positions)
+                    .with_wrap_mode(wrap_mode)
+                    .with_direction(position.direction);
+                let rest = resumed.handle_input(std::io::BufReader::new(second.as_bytes()));
+                assert_eq!(
+                    (counts.0 + rest.0, counts.1 + rest.1),
+                    expected,
+                    "{wrap_mode:?} split at {split}"
+                );
+                assert_eq!(resumed.state(), whole.state());
+            }
+        }
+        // A bare magnitude at the start of a chunk continues the previous chunk's direction.
+        let mut position = Position::new(50, 100);
+        position.handle_input(std::io::BufReader::new("L10".as_bytes()));
+        let (current, total_positions) = position.state();
+        let mut resumed =
+            Position::from_state(current, total_positions).with_direction(position.direction);
+        resumed.handle_input(std::io::BufReader::new("20".as_bytes()));
+        assert_eq!(resumed.state(), (20, 100));
+    }
+
+    const MALFORMED_INPUT: &str = "
+L68
+L30
+X30
+R48
+L5
+R
+Lfive
+R60
+L55
+L1
+L99
+R14
+L82";
+
+    #[test]
+    fn test_handle_input_with_skipped() {
+        let test_input = std::io::BufReader::new(MALFORMED_INPUT.as_bytes());
+        let (exact, passthroughs, skipped) =
+            super::Position::new(50, 100).handle_input_with_skipped(test_input);
+        assert_eq!(skipped, 3);
+        assert_eq!((exact, passthroughs), (3, 6));
+    }
+
+    #[test]
+    fn test_direction_persistence() {
+        for (compact, expanded) in [
+            ("L68,30", "L68\nL30"),
+            ("L68,30,R48,L5,R60,L55,1,99,R14,L82", EXAMPLE_INPUT),
+            ("30, L5 ,10", "R30\nL5\nL10"),
+            ("L5\n10,R1\n2", "L5\nL10\nR1\nR2"),
+            ("L5,2x10,R3,2xL1,4", "L5\n2xL10\nR3\n2xL1\nL4"),
+        ] {
+            for start in [0, 50, 75] {
+                let result = super::Position::new(start, 100)
+                    .summarize(std::io::BufReader::new(compact.as_bytes()));
+                let expected = super::Position::new(start, 100)
+                    .summarize(std::io::BufReader::new(expanded.as_bytes()));
+                assert_eq!(result, expected, "{compact} from {start}");
+            }
+        }
+
+        let test_input = std::io::BufReader::new("L5,,X3,7".as_bytes());
+        let summary = super::Position::new(50, 100).summarize(test_input);
+        assert_eq!((summary.net, summary.skipped), (-12, 1));
+    }
+
+    #[test]
+    fn test_summarize() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let mut position = super::Position::new(50, 100);
+        assert_eq!(
+            position.summarize(test_input),
+            super::RunSummary {
+                rotations: 10,
+                net: -218,
+                exact: 3,
+                passthrough: 6,
+                revolutions: 2,
+                final_position: 32,
+                skipped: 0,
+            }
+        );
+        assert_eq!(position.current, 32);
+
+        let test_input = std::io::BufReader::new(MALFORMED_INPUT.as_bytes());
+        let summary = super::Position::new(50, 100).summarize(test_input);
+        assert_eq!((summary.rotations, summary.skipped), (10, 3));
+
+        let test_input = std::io::BufReader::new("3xR2000000000".as_bytes());
+        let summary = super::Position::new(0, 100).summarize(test_input);
+        assert_eq!(summary.net, 6_000_000_000);
+    }
+
+    #[test]
+    fn test_summarize_revolutions() {
+        // R150 reaches 200 and L30 stays within the dial, R60 reaches 130, and L250 reaches -220,
+        // which crosses zero three times but only spans two whole turns below it.
+        let test_input = std::io::BufReader::new("R150\nL30\nR60\nL250\n".as_bytes());
+        let summary = super::Position::new(50, 100).summarize(test_input);
+        assert_eq!(summary.revolutions, 5);
+        assert_eq!(summary.passthrough, 6);
+        assert_eq!(summary.final_position, 80);
+    }
+
+    #[test]
+    fn test_minimal_equivalent() {
+        let position = super::Position::new(50, 100);
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let rot = position.minimal_equivalent(test_input);
+        assert_eq!(rot.0, -18);
+
+        let mut replayed = super::Position::new(50, 100);
+        replayed.handle_rotation(&rot);
+        let mut full = super::Position::new(50, 100);
+        full.handle_input(std::io::BufReader::new(EXAMPLE_INPUT.as_bytes()));
+        assert_eq!(replayed.current, full.current);
+
+        for (input, expected) in [
+            ("R60", -40),
+            ("R50", 50),
+            ("L50", 50),
+            ("L49", -49),
+            ("3xR100\nL1", -1),
+            ("", 0),
+        ] {
+            let test_input = std::io::BufReader::new(input.as_bytes());
+            assert_eq!(
+                position.minimal_equivalent(test_input).0,
+                expected,
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_cancellations() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        assert_eq!(super::count_cancellations(test_input), 0);
+
+        const CANCELLING_INPUT: &str = "
+R48
+L48
+L5
+X5
+R5
+R10
+L10
+R10
+R0
+L0
+2xR3
+";
+        let test_input = std::io::BufReader::new(CANCELLING_INPUT.as_bytes());
+        assert_eq!(super::count_cancellations(test_input), 4);
+    }
+
+    #[test]
+    fn test_longest_same_direction_run() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        // L55, L1 and L99
+        assert_eq!(super::longest_same_direction_run(test_input), 3);
+
+        const RUN_INPUT: &str = "
+R5
+R6
+X5
+R7
+L1
+R2,R3
+2xR4
+R0
+R8
+R9
+";
+        let test_input = std::io::BufReader::new(RUN_INPUT.as_bytes());
+        assert_eq!(super::longest_same_direction_run(test_input), 4);
+
+        let test_input = std::io::BufReader::new("".as_bytes());
+        assert_eq!(super::longest_same_direction_run(test_input), 0);
+        let test_input = std::io::BufReader::new("R0\nL0\n".as_bytes());
+        assert_eq!(super::longest_same_direction_run(test_input), 0);
+    }
+
+    #[test]
+    fn test_rotations_until_zero() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let mut position = super::Position::new(50, 100);
+        assert_eq!(position.rotations_until_zero(test_input), Some(3));
+        assert_eq!(position.current, 0);
+
+        let test_input = std::io::BufReader::new("R1\nL2\nR99".as_bytes());
+        let result = super::Position::new(50, 100).rotations_until_zero(test_input);
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_starts_with_exact_count() {
+        let result = super::starts_with_exact_count(EXAMPLE_INPUT, 100, 3);
+        assert_eq!(result, vec![50]);
+        let result = super::starts_with_exact_count(EXAMPLE_INPUT, 100, 2);
+        assert_eq!(result, vec![]);
+        let result = super::starts_with_exact_count("R1\nR1", 4, 1);
+        assert_eq!(result, vec![2, 3]);
+        let result = super::starts_with_exact_count("R1\nR1", 4, 0);
+        assert_eq!(result, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_handle_rotation_clamp() {
+        for (start, rot, expected, end) in [
+            (2, -1, (0, 0), 1),
+            (2, -2, (1, 1), 0),
+            (2, -10, (1, 1), 0),
+            (2, 10, (0, 0), 4),
+            (0, -3, (1, 0), 0),
+            (0, 3, (0, 0), 3),
+        ] {
+            let mut position =
+                super::Position::new(start, 5).with_wrap_mode(super::WrapMode::Clamp);
+            let result = position.handle_rotation(&super::Rotation(rot));
+            assert_eq!((result, position.current), (expected, end), "{start} {rot}");
+        }
+    }
+
+    #[test]
+    fn test_handle_rotation_bounce() {
+        for (start, rot, expected, end) in [
+            (2, 1, (0, 0), 3),
+            (2, 3, (0, 0), 3),
+            (2, 6, (1, 1), 0),
+            (2, 10, (0, 1), 4),
+            (2, -2, (1, 1), 0),
+            (2, -3, (0, 1), 1),
+            (2, -18, (1, 3), 0),
+            (0, 8, (1, 1), 0),
+            (0, -1, (0, 0), 1),
+            (4, 1, (0, 0), 3),
+        ] {
+            let mut position =
+                super::Position::new(start, 5).with_wrap_mode(super::WrapMode::Bounce);
+            let result = position.handle_rotation(&super::Rotation(rot));
+            assert_eq!((result, position.current), (expected, end), "{start} {rot}");
+        }
+    }
+
+    #[test]
+    fn test_handle_rotation_wrap_mode_default() {
+        let mut position = super::Position::new(2, 5);
+        assert_eq!(position.wrap_mode, super::WrapMode::Wrap);
+        assert_eq!(position.handle_rotation(&super::Rotation(-3)), (0, 1));
+        assert_eq!(position.current, 4);
+    }
+
+    #[test]
+    fn test_repeated_rotation() {
+        for (repeated, expanded) in [
+            ("3xL68", "L68\nL68\nL68"),
+            ("4xR25", "R25\nR25\nR25\nR25"),
+            ("2xR100", "R100\nR100"),
+            ("1xL5\n0xR10\nL1", "L5\nL1"),
+        ] {
+            for start in [0, 18, 50, 75] {
+                let result = super::Position::new(start, 100)
+                    .handle_input_with_skipped(std::io::BufReader::new(repeated.as_bytes()));
+                let expected = super::Position::new(start, 100)
+                    .handle_input_with_skipped(std::io::BufReader::new(expanded.as_bytes()));
+                assert_eq!(result, expected, "{repeated} from {start}");
+            }
+        }
+        // Unlike a single R200, each R100 from zero lands on zero.
+        let test_input = std::io::BufReader::new("2xR100".as_bytes());
+        assert_eq!(
+            super::Position::new(0, 100).handle_input(test_input),
+            (2, 2)
+        );
+        let test_input = std::io::BufReader::new("R200".as_bytes());
+        assert_eq!(
+            super::Position::new(0, 100).handle_input(test_input),
+            (1, 2)
+        );
+
+        let test_input = std::io::BufReader::new("xL5\n2yL5\n2xQ5".as_bytes());
+        let (_, _, skipped) = super::Position::new(50, 100).handle_input_with_skipped(test_input);
+        assert_eq!(skipped, 3);
+    }
+
+    /// Simulates the rotation one tick at a time on a wrapping dial, returning whether it ends on
+    /// zero, the number of ticks which land on zero, and the final position.
+    fn brute_force_rotation(start: i32, rot: i32, total_positions: i32) -> (usize, usize, i32) {
+        let step = rot.signum();
+        let mut current = start;
+        let mut passthroughs = 0;
+        for _ in 0..rot.unsigned_abs() {
+            current = (current + step).rem_euclid(total_positions);
+            if current == 0 {
+                passthroughs += 1;
+            }
+        }
+        let exact = if current == 0 { 1 } else { 0 };
+        (exact, passthroughs, current)
+    }
+
+    /// Advances the xorshift `seed` and maps it into `[low, high)`.
+    fn random_in(seed: &mut u64, low: i32, high: i32) -> i32 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        low + (*seed % (high - low) as u64) as i32
+    }
+
+    #[test]
+    fn test_handle_rotation_matches_brute_force() {
+        let mut seed: u64 = 0x2025_1201;
+        for _ in 0..2000 {
+            let total_positions = match random_in(&mut seed, 0, 4) {
+                0 => random_in(&mut seed, 1, 4),
+                _ => random_in(&mut seed, 1, 200),
+            };
+            let start = random_in(&mut seed, 0, total_positions);
+            let magnitude = match random_in(&mut seed, 0, 10) {
+                0 => random_in(&mut seed, 0, 100_000),
+                1 => total_positions * random_in(&mut seed, 0, 50),
+                _ => random_in(&mut seed, 0, 3 * total_positions + 1),
+            };
+            let rot = if random_in(&mut seed, 0, 2) == 0 {
+                magnitude
+            } else {
+                -magnitude
+            };
+            let (exact, passthroughs, end) = brute_force_rotation(start, rot, total_positions);
+            let mut position = super::Position::new(start, total_positions);
+            let result = position.handle_rotation(&super::Rotation(rot));
+            assert_eq!(
+                (result, position.current),
+                ((exact, passthroughs), end),
+                "start {start}, rotation {rot}, dial size {total_positions}"
+            );
+        }
+    }
 }
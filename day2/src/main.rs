@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+use std::io::Read;
 use std::num::ParseIntError;
 
 /// Returns true if the number is two repeating sequences of digits. For example, 99, or 1212, or
@@ -12,9 +14,15 @@ fn is_invalid(num: usize) -> bool {
 }
 
 fn is_invalid_2(num: usize) -> bool {
+    invalid_block(num).is_some()
+}
+
+/// Returns the shortest block of digits which the number repeats at least twice, such as 12 for
+/// 121212, or [None] if the number is valid by the part 2 definition.
+fn invalid_block(num: usize) -> Option<usize> {
     let length = num.ilog10() + 1;
     if length < 2 {
-        return false;
+        return None;
     }
     for candidate in (1..=(length / 2)).filter(|x| length.is_multiple_of(*x)) {
         // Only need to check prime factors, but easier to just check all
@@ -25,15 +33,27 @@ fn is_invalid_2(num: usize) -> bool {
             .map(|y| (num / base10mask.pow(y)) % base10mask) // shift right by y mask-widths and mask
             .all(|z| z == target)
         {
-            return true;
+            return Some(target);
         }
     }
-    false
+    None
 }
 
+#[derive(Debug, PartialEq)]
 enum ParseRangeError {
     ParseNums,
     ParseInt(ParseIntError),
+    Overflow,
+}
+
+/// Parses a bound which is either a decimal literal or a `base^exp` power, such as `2^10`.
+fn parse_bound(s: &str) -> Result<usize, ParseRangeError> {
+    let Some((base, exp)) = s.split_once('^') else {
+        return s.parse().map_err(ParseRangeError::ParseInt);
+    };
+    let base: usize = base.parse().map_err(ParseRangeError::ParseInt)?;
+    let exp: u32 = exp.parse().map_err(ParseRangeError::ParseInt)?;
+    base.checked_pow(exp).ok_or(ParseRangeError::Overflow)
 }
 
 fn parse_range(s: &str) -> Result<(usize, usize), ParseRangeError> {
@@ -41,48 +61,211 @@ fn parse_range(s: &str) -> Result<(usize, usize), ParseRangeError> {
     if nums.len() != 2 {
         return Err(ParseRangeError::ParseNums);
     }
-    let start: usize = nums[0].parse().map_err(ParseRangeError::ParseInt)?;
-    let end: usize = nums[1].parse().map_err(ParseRangeError::ParseInt)?;
+    let start = parse_bound(nums[0])?;
+    let end = parse_bound(nums[1])?;
     Ok((start, end))
 }
 
+/// Returns the portion of the line before any trailing `;` annotation.
+fn strip_annotation(line: &str) -> &str {
+    line.split_once(';').map_or(line, |(data, _)| data)
+}
+
+/// Lines beginning with `#` are treated as comments and skipped entirely, and anything following
+/// a `;` on a line is treated as an annotation and ignored.
+///
+/// Malformed ranges are silently skipped; see [find_all_ranges_checked] to report them instead.
 fn find_all_ids(r: impl std::io::BufRead) -> impl Iterator<Item = usize> {
+    find_all_ranges_checked(r)
+        .filter_map(Result::ok)
+        .flat_map(|(start, end)| start..=end)
+}
+
+/// Yields every range in the input, or an error for each malformed range tagged with the 0-based
+/// `(line, field)` at which it appears, where fields are the comma-separated entries of the line.
+/// Comments and annotations are skipped as in [find_all_ids], and an error does not stop the
+/// iteration.
+fn find_all_ranges_checked(
+    r: impl std::io::BufRead,
+) -> impl Iterator<Item = Result<(usize, usize), ((usize, usize), ParseRangeError)>> {
     r.lines()
         .map_while(Result::ok)
-        .flat_map(|line| {
-            line.split(',')
-                .filter(|entry| !entry.is_empty())
-                .map(parse_range)
-                .filter_map(Result::ok)
+        .enumerate()
+        .filter(|(_, line)| !line.trim_start().starts_with('#'))
+        .flat_map(|(line_index, line)| {
+            strip_annotation(&line)
+                .split(',')
+                .map(str::trim)
+                .enumerate()
+                .filter(|(_, entry)| !entry.is_empty())
+                .map(|(field, entry)| parse_range(entry).map_err(|e| ((line_index, field), e)))
                 .collect::<Vec<_>>()
         })
-        .flat_map(|(start, end)| start..=end)
 }
 
 fn filter_invalid_ids(ids: impl Iterator<Item = usize>) -> impl Iterator<Item = usize> {
     ids.filter(|id| is_invalid(*id))
 }
 
+#[cfg_attr(not(test), allow(dead_code))]
 fn filter_invalid_ids_2(ids: impl Iterator<Item = usize>) -> impl Iterator<Item = usize> {
     ids.filter(|id| is_invalid_2(*id))
 }
 
+/// The complement of [filter_invalid_ids], yielding the IDs which are valid by the part 1
+/// definition.
+fn filter_valid_ids(ids: impl Iterator<Item = usize>) -> impl Iterator<Item = usize> {
+    ids.filter(|id| !is_invalid(*id))
+}
+
+/// The complement of [filter_invalid_ids_2], yielding the IDs which are valid by the part 2
+/// definition.
+fn filter_valid_ids_2(ids: impl Iterator<Item = usize>) -> impl Iterator<Item = usize> {
+    ids.filter(|id| !is_invalid_2(*id))
+}
+
+/// Lazily yields each invalid ID (by the part 2 definition) along with its [invalid_block], as
+/// `(block, id)` pairs in input order, for ranges too large to group in memory.
+#[cfg_attr(not(test), allow(dead_code))]
+fn invalid_ids_with_block(
+    ids: impl Iterator<Item = usize>,
+) -> impl Iterator<Item = (usize, usize)> {
+    ids.filter_map(|id| invalid_block(id).map(|block| (block, id)))
+}
+
+/// Groups the invalid IDs (by the part 2 definition) by their [invalid_block], keeping the IDs of
+/// each block in input order.
+#[cfg_attr(not(test), allow(dead_code))]
+fn group_invalid_by_block(ids: impl Iterator<Item = usize>) -> BTreeMap<usize, Vec<usize>> {
+    invalid_ids_with_block(ids).fold(BTreeMap::new(), |mut acc, (block, id)| {
+        acc.entry(block).or_insert_with(Vec::new).push(id);
+        acc
+    })
+}
+
+/// Counts the invalid IDs (by the part 1 definition) by their number of digits.
+#[cfg_attr(not(test), allow(dead_code))]
+fn invalid_length_histogram(ids: impl Iterator<Item = usize>) -> BTreeMap<u32, usize> {
+    filter_invalid_ids(ids).fold(BTreeMap::new(), |mut acc, id| {
+        *acc.entry(id.ilog10() + 1).or_insert(0) += 1;
+        acc
+    })
+}
+
+/// Returns the start of the window `[start, start + window)` containing the most distinct invalid
+/// IDs (by the part 1 definition), along with the number of invalid IDs it contains. Ties are
+/// broken toward the lowest start. Returns [None] if there are no invalid IDs or the window is
+/// empty.
+#[cfg_attr(not(test), allow(dead_code))]
+fn densest_invalid_window(
+    ids: impl Iterator<Item = usize>,
+    window: usize,
+) -> Option<(usize, usize)> {
+    if window == 0 {
+        return None;
+    }
+    let mut invalid: Vec<usize> = filter_invalid_ids(ids).collect();
+    invalid.sort_unstable();
+    invalid.dedup();
+    // The densest window can always be shifted right until it starts at an invalid ID.
+    let mut best: Option<(usize, usize)> = None;
+    let mut end = 0;
+    for (i, &start) in invalid.iter().enumerate() {
+        while end < invalid.len() && invalid[end] - start < window {
+            end += 1;
+        }
+        let count = end - i;
+        if best.is_none_or(|(_, best_count)| count > best_count) {
+            best = Some((start, count));
+        }
+    }
+    best
+}
+
+/// Returns the sums of the invalid IDs by the part 1 and part 2 definitions.
+fn sum_invalid(ids: impl Iterator<Item = usize>) -> (usize, usize) {
+    ids.fold((0, 0), |acc, id| match (is_invalid(id), is_invalid_2(id)) {
+        (true, true) => (acc.0 + id, acc.1 + id),
+        (true, false) => (acc.0 + id, acc.1),
+        (false, true) => (acc.0, acc.1 + id),
+        _ => acc,
+    })
+}
+
+/// Sums the invalid IDs (by the part 1 definition), stopping as soon as adding the next invalid ID
+/// would take the sum past the budget, in which case the partial sum so far is returned as the
+/// error. Since it stops early, a runaway input is never summed in full.
+fn sum_invalid_until(ids: impl Iterator<Item = usize>, budget: usize) -> Result<usize, usize> {
+    filter_invalid_ids(ids).try_fold(0_usize, |acc, id| {
+        acc.checked_add(id).filter(|sum| *sum <= budget).ok_or(acc)
+    })
+}
+
+/// Splits `[start, end]` into `jobs` contiguous chunks and sums the invalid IDs in each chunk on
+/// its own thread, returning the combined sums by the part 1 and part 2 definitions.
+#[cfg_attr(not(test), allow(dead_code))]
+#[cfg(feature = "parallel")]
+fn sum_invalid_range_parallel(start: usize, end: usize, jobs: usize) -> (usize, usize) {
+    if start > end {
+        return (0, 0);
+    }
+    let chunk = (end - start).saturating_add(1).div_ceil(jobs.max(1));
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (start..=end)
+            .step_by(chunk)
+            .map(|chunk_start| {
+                let chunk_end = chunk_start.saturating_add(chunk - 1).min(end);
+                scope.spawn(move || sum_invalid(chunk_start..=chunk_end))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .fold((0, 0), |acc, sums| (acc.0 + sums.0, acc.1 + sums.1))
+    })
+}
+
 fn main() {
-    let (pt1, pt2) = find_all_ids(std::io::stdin().lock()).fold((0, 0), |acc, id| {
-        match (is_invalid(id), is_invalid_2(id)) {
-            (true, true) => (acc.0 + id, acc.1 + id),
-            (true, false) => (acc.0 + id, acc.1),
-            (false, true) => (acc.0, acc.1 + id),
-            _ => acc,
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--budget") {
+        let budget: usize = args
+            .get(i + 1)
+            .and_then(|val| val.parse().ok())
+            .unwrap_or_else(|| {
+                eprintln!("--budget requires a non-negative integer");
+                std::process::exit(1);
+            });
+        match sum_invalid_until(find_all_ids(std::io::stdin().lock()), budget) {
+            Ok(pt1) => println!("sum of invalid IDs part 1: {pt1}"),
+            Err(partial) => {
+                println!("sum of invalid IDs part 1: exceeded budget of {budget} after {partial}")
+            }
         }
-    });
+        return;
+    }
+    if args.iter().any(|arg| arg == "--valid") {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input).unwrap();
+        let ids = || find_all_ids(std::io::BufReader::new(input.as_bytes()));
+        let pt1: usize = filter_valid_ids(ids()).sum();
+        let pt2: usize = filter_valid_ids_2(ids()).sum();
+        println!("sum of valid IDs part 1: {pt1}");
+        println!("sum of valid IDs part 2: {pt2}");
+        return;
+    }
+    let (pt1, pt2) = sum_invalid(find_all_ids(std::io::stdin().lock()));
     println!("sum of invalid IDs part 1: {pt1}");
     println!("sum of invalid IDs part 2: {pt2}");
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{filter_invalid_ids, filter_invalid_ids_2, find_all_ids, is_invalid, is_invalid_2};
+    use crate::{
+        ParseRangeError, densest_invalid_window, filter_invalid_ids, filter_invalid_ids_2,
+        filter_valid_ids, filter_valid_ids_2, find_all_ids, find_all_ranges_checked,
+        group_invalid_by_block, invalid_block, invalid_ids_with_block, invalid_length_histogram,
+        is_invalid, is_invalid_2, parse_range, sum_invalid, sum_invalid_until,
+    };
 
     const SIMPLE_INPUT: &str = "2-5,9-11";
     const EXAMPLE_ONELINE: &str = "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124";
@@ -91,6 +274,40 @@ mod tests {
 1698522-1698528,446443-446449,38593856-38593862,565653-565659,
 824824821-824824827,2121212118-2121212124";
 
+    const COMMENTED_INPUT: &str = "
+# first batch
+2-5, 9-11 ; small ones
+;just a note
+  # indented comment
+95-99;
+";
+
+    #[test]
+    fn test_find_all_ids_with_comments() {
+        let input = std::io::BufReader::new(COMMENTED_INPUT.as_bytes());
+        let result: Vec<usize> = find_all_ids(input).collect();
+        assert_eq!(result, vec![2, 3, 4, 5, 9, 10, 11, 95, 96, 97, 98, 99]);
+    }
+
+    #[test]
+    fn test_find_all_ranges_checked() {
+        let input = std::io::BufReader::new("\n# comment\n2-5, 9-x ; note\n,95-99,7\n".as_bytes());
+        let result: Vec<_> = find_all_ranges_checked(input).collect();
+        assert!(matches!(
+            result[..],
+            [
+                Ok((2, 5)),
+                Err(((2, 1), ParseRangeError::ParseInt(_))),
+                Ok((95, 99)),
+                Err(((3, 2), ParseRangeError::ParseNums)),
+            ]
+        ));
+
+        // The lenient reader skips the malformed ranges.
+        let input = std::io::BufReader::new("2-5, 9-x\n".as_bytes());
+        assert_eq!(find_all_ids(input).collect::<Vec<_>>(), vec![2, 3, 4, 5]);
+    }
+
     #[test]
     fn test_is_invalid() {
         let expected: Vec<(usize, bool)> = vec![
@@ -209,4 +426,150 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_filter_valid_ids() {
+        let ids = || find_all_ids(std::io::BufReader::new(SIMPLE_INPUT.as_bytes()));
+        let result: Vec<usize> = filter_valid_ids(ids()).collect();
+        assert_eq!(result, vec![2, 3, 4, 5, 9, 10]);
+        let result: Vec<usize> = filter_valid_ids_2(ids()).collect();
+        assert_eq!(result, vec![2, 3, 4, 5, 9, 10]);
+    }
+
+    #[test]
+    fn test_valid_and_invalid_partition_ids() {
+        for input in [SIMPLE_INPUT, EXAMPLE_ONELINE] {
+            let ids = || find_all_ids(std::io::BufReader::new(input.as_bytes()));
+            let mut all: Vec<usize> = ids().collect();
+            all.sort_unstable();
+            for (mut valid, invalid) in [
+                (
+                    filter_valid_ids(ids()).collect::<Vec<_>>(),
+                    filter_invalid_ids(ids()).collect::<Vec<_>>(),
+                ),
+                (
+                    filter_valid_ids_2(ids()).collect(),
+                    filter_invalid_ids_2(ids()).collect(),
+                ),
+            ] {
+                assert_eq!(valid.len() + invalid.len(), all.len());
+                assert!(valid.iter().all(|id| !invalid.contains(id)));
+                valid.extend(invalid);
+                valid.sort_unstable();
+                assert_eq!(valid, all);
+            }
+        }
+    }
+
+    #[test]
+    fn test_densest_invalid_window() {
+        let ids = || find_all_ids(std::io::BufReader::new(SIMPLE_INPUT.as_bytes()));
+        assert_eq!(densest_invalid_window(ids(), 3), Some((11, 1)));
+        assert_eq!(densest_invalid_window(ids(), 0), None);
+
+        let ids = || find_all_ids(std::io::BufReader::new(EXAMPLE_ONELINE.as_bytes()));
+        assert_eq!(densest_invalid_window(ids(), 5), Some((11, 1)));
+        assert_eq!(densest_invalid_window(ids(), 12), Some((11, 2)));
+        assert_eq!(densest_invalid_window(ids(), 89), Some((11, 3)));
+        assert_eq!(densest_invalid_window(ids(), 1000), Some((11, 4)));
+
+        let ids = std::iter::empty();
+        assert_eq!(densest_invalid_window(ids, 10), None);
+    }
+
+    #[test]
+    fn test_parse_range_powers() {
+        assert_eq!(parse_range("2^10-2^20"), Ok((1024, 1048576)));
+        assert_eq!(parse_range("2^3-20"), Ok((8, 20)));
+        assert_eq!(parse_range("5-10^2"), Ok((5, 100)));
+        assert_eq!(parse_range("11-22"), Ok((11, 22)));
+        assert_eq!(parse_range("1-2^64"), Err(ParseRangeError::Overflow));
+        assert!(matches!(
+            parse_range("2^x-5"),
+            Err(ParseRangeError::ParseInt(_))
+        ));
+
+        let input = std::io::BufReader::new("2^3-10,3^2-2^4".as_bytes());
+        let result: Vec<usize> = filter_invalid_ids(find_all_ids(input)).collect();
+        assert_eq!(result, vec![11]);
+    }
+
+    #[test]
+    fn test_invalid_block() {
+        assert_eq!(invalid_block(222222), Some(2));
+        assert_eq!(invalid_block(121212), Some(12));
+        assert_eq!(invalid_block(1188511885), Some(11885));
+        assert_eq!(invalid_block(7), None);
+        assert_eq!(invalid_block(1213), None);
+    }
+
+    #[test]
+    fn test_group_invalid_by_block() {
+        let input = std::io::BufReader::new(EXAMPLE_ONELINE.as_bytes());
+        let result = group_invalid_by_block(find_all_ids(input));
+        assert_eq!(
+            result.into_iter().collect::<Vec<_>>(),
+            vec![
+                (1, vec![11, 111]),
+                (2, vec![22, 222222]),
+                (9, vec![99, 999]),
+                (10, vec![1010]),
+                (21, vec![2121212121]),
+                (56, vec![565656]),
+                (446, vec![446446]),
+                (824, vec![824824824]),
+                (3859, vec![38593859]),
+                (11885, vec![1188511885]),
+            ]
+        );
+
+        let input = std::io::BufReader::new(EXAMPLE_ONELINE.as_bytes());
+        let streamed: Vec<(usize, usize)> = invalid_ids_with_block(find_all_ids(input))
+            .take(3)
+            .collect();
+        assert_eq!(streamed, vec![(1, 11), (2, 22), (9, 99)]);
+    }
+
+    #[test]
+    fn test_invalid_length_histogram() {
+        let input = std::io::BufReader::new(EXAMPLE_ONELINE.as_bytes());
+        let result = invalid_length_histogram(find_all_ids(input));
+        assert_eq!(
+            result.into_iter().collect::<Vec<_>>(),
+            vec![(2, 3), (4, 1), (6, 2), (8, 1), (10, 1)]
+        );
+    }
+
+    #[test]
+    fn test_sum_invalid() {
+        let input = std::io::BufReader::new(EXAMPLE_ONELINE.as_bytes());
+        assert_eq!(sum_invalid(find_all_ids(input)), (1227775554, 4174379265));
+    }
+
+    #[test]
+    fn test_sum_invalid_until() {
+        let ids = || find_all_ids(std::io::BufReader::new(EXAMPLE_ONELINE.as_bytes()));
+        assert_eq!(sum_invalid_until(ids(), usize::MAX), Ok(1227775554));
+        assert_eq!(sum_invalid_until(ids(), 1227775554), Ok(1227775554));
+        // 11 + 22 + 99 + 1010 fit, but the next invalid ID, 1188511885, does not
+        assert_eq!(sum_invalid_until(ids(), 1000000), Err(1142));
+        assert_eq!(sum_invalid_until(ids(), 10), Err(0));
+        assert_eq!(sum_invalid_until(std::iter::empty(), 0), Ok(0));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_sum_invalid_range_parallel() {
+        use crate::sum_invalid_range_parallel;
+        let expected = sum_invalid(1..=123456);
+        for jobs in [0, 1, 2, 3, 4, 7, 16] {
+            assert_eq!(sum_invalid_range_parallel(1, 123456, jobs), expected);
+        }
+        assert_eq!(
+            sum_invalid_range_parallel(95, 115, 100),
+            sum_invalid(95..=115)
+        );
+        assert_eq!(sum_invalid_range_parallel(11, 11, 4), (11, 11));
+        assert_eq!(sum_invalid_range_parallel(12, 11, 4), (0, 0));
+    }
 }
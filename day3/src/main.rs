@@ -1,60 +1,402 @@
+use std::collections::HashMap;
 use std::num::ParseIntError;
 
 #[derive(Debug, PartialEq)]
 enum ParseBatteryError {
     TooShort,
     ParseBattery,
+    WeightCount,
     ParseInt(ParseIntError),
 }
 
-// Naive, simple approach which is O(N*M) for len N and line with length M. But it doesn't matter,
-// Rust is fast.
-fn max_battery_of_length(len: usize, line: &str) -> Result<usize, ParseBatteryError> {
+/// Which occurrence of the greatest digit in the selection window is chosen when it appears more
+/// than once. Choosing the earliest leaves the most digits available for the rest of the battery,
+/// so it always yields the greatest battery.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum TieBreak {
+    #[default]
+    Earliest,
+    #[cfg_attr(not(test), allow(dead_code))]
+    Latest,
+}
+
+impl TieBreak {
+    fn select(self, window: &[u8]) -> Option<(usize, u8)> {
+        match self {
+            TieBreak::Earliest => earliest_max(window),
+            TieBreak::Latest => latest_max(window),
+        }
+    }
+}
+
+/// Selects the lexicographically greatest subsequence of the given length from the line, breaking
+/// ties between equal digits according to `tie_break`. The line must consist only of digits, so
+/// that it can be scanned as bytes.
+fn select_battery(
+    len: usize,
+    line: &str,
+    tie_break: TieBreak,
+) -> Result<String, ParseBatteryError> {
+    let bytes = line.as_bytes();
+    Ok(select_battery_indices(len, line, tie_break)?
+        .into_iter()
+        .map(|i| char::from(bytes[i]))
+        .collect())
+}
+
+/// Like [select_battery], but returns the ascending positions of the selected digits in the line,
+/// breaking ties between equal digits according to `tie_break`. Each selection starts just after
+/// the previous one, so choosing a later digit narrows the windows for the rest.
+fn select_battery_indices(
+    len: usize,
+    line: &str,
+    tie_break: TieBreak,
+) -> Result<Vec<usize>, ParseBatteryError> {
     if line.len() < len {
         return Err(ParseBatteryError::TooShort);
     }
-    let mut digits = String::new();
-    let mut prev_index: isize = -1; // a hack so we start looking at 0
+    // checked up front, since a non-digit byte could otherwise compare greater than a `9`
+    if !line.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ParseBatteryError::ParseBattery);
+    }
+    let bytes = line.as_bytes();
+    // Scanning a window per digit is O(N*M) for len N and line with length M in the worst case,
+    // though an earliest tie-break stops each window at its first `9`.
+    let mut indices = Vec::with_capacity(len);
+    let mut start_index = 0;
     for i in 0..len {
-        let start_index = (prev_index + 1) as usize;
-        let (greatest, neg_ind) = line
-            .get(start_index..(line.len() - len + 1 + i))
-            .ok_or(ParseBatteryError::ParseBattery)?
-            .chars()
-            .enumerate()
-            .map(|(ind, byt)| (byt, -(ind as isize)))
-            .max()
+        let window = &bytes[start_index..(bytes.len() - len + 1 + i)];
+        let (offset, _) = tie_break
+            .select(window)
             .ok_or(ParseBatteryError::ParseBattery)?;
-        digits.push(greatest);
-        prev_index = start_index as isize - neg_ind;
+        indices.push(start_index + offset);
+        start_index += offset + 1;
     }
-    digits.parse().map_err(ParseBatteryError::ParseInt)
+    Ok(indices)
 }
 
-fn extract_batteries(r: impl std::io::BufRead) -> impl Iterator<Item = (usize, usize)> {
+/// Returns the position and value of the greatest byte in the window, breaking ties toward the
+/// earliest position. Stops early on a `9`, since no digit can beat it.
+fn earliest_max(window: &[u8]) -> Option<(usize, u8)> {
+    let mut best: Option<(usize, u8)> = None;
+    for (i, &b) in window.iter().enumerate() {
+        if best.is_none_or(|(_, greatest)| b > greatest) {
+            best = Some((i, b));
+            if b == b'9' {
+                break;
+            }
+        }
+    }
+    best
+}
+
+/// Returns the position and value of the greatest byte in the window, breaking ties toward the
+/// latest position. The whole window must be scanned, since a later `9` would win.
+fn latest_max(window: &[u8]) -> Option<(usize, u8)> {
+    let mut best: Option<(usize, u8)> = None;
+    for (i, &b) in window.iter().enumerate() {
+        if best.is_none_or(|(_, greatest)| b >= greatest) {
+            best = Some((i, b));
+        }
+    }
+    best
+}
+
+/// When `reverse` is true, the line is read from right to left, so the number is formed from the
+/// chosen digits in reversed order. Ties between equal digits are broken according to
+/// `tie_break`, though only [TieBreak::Earliest] is guaranteed to give the greatest battery.
+fn max_battery_of_length(
+    len: usize,
+    line: &str,
+    reverse: bool,
+    tie_break: TieBreak,
+) -> Result<usize, ParseBatteryError> {
+    max_battery_digits(len, line, reverse, tie_break).map(|(_, val)| val)
+}
+
+/// Like [max_battery_of_length], but the excluded characters, such as dead cells, are removed from
+/// the line before selecting, so they can never be chosen. Returns
+/// [ParseBatteryError::TooShort] if fewer than `len` characters remain.
+#[cfg_attr(not(test), allow(dead_code))]
+fn max_battery_excluding(
+    len: usize,
+    line: &str,
+    excluded: &[char],
+) -> Result<usize, ParseBatteryError> {
+    let usable: String = line.chars().filter(|c| !excluded.contains(c)).collect();
+    max_battery_of_length(len, &usable, false, TieBreak::default())
+}
+
+/// Sums the digit values of the battery which [max_battery_of_length] would select, without
+/// forming the number itself.
+#[cfg_attr(not(test), allow(dead_code))]
+fn battery_digit_sum(len: usize, line: &str) -> Result<usize, ParseBatteryError> {
+    select_battery(len, line, TieBreak::default())?
+        .chars()
+        .map(|c| c.to_digit(10).map(|d| d as usize))
+        .sum::<Option<usize>>()
+        .ok_or(ParseBatteryError::ParseBattery)
+}
+
+/// Returns the greatest battery of the given length whose digits are strictly increasing, or
+/// `None` if the line has no such subsequence. Since a staircase of digits has at most ten steps,
+/// any longer length is always `None`.
+#[cfg_attr(not(test), allow(dead_code))]
+fn max_increasing_battery(len: usize, line: &str) -> Result<Option<usize>, ParseBatteryError> {
+    if line.len() < len {
+        return Err(ParseBatteryError::TooShort);
+    }
+    let digits: Vec<u8> = line
+        .chars()
+        .map(|c| c.to_digit(10).map(|d| d as u8))
+        .collect::<Option<_>>()
+        .ok_or(ParseBatteryError::ParseBattery)?;
+    if len > 10 {
+        return Ok(None);
+    }
+    // longest[i] is the length of the longest staircase starting with the digit at i
+    let mut longest = vec![1; digits.len()];
+    for i in (0..digits.len()).rev() {
+        for j in (i + 1)..digits.len() {
+            if digits[j] > digits[i] {
+                longest[i] = longest[i].max(longest[j] + 1);
+            }
+        }
+    }
+    // Greedily take the greatest next digit which can still start a long enough staircase,
+    // preferring its earliest occurrence, which leaves the most room for the remaining steps.
+    let mut value = 0;
+    let mut start = 0;
+    let mut prev: Option<u8> = None;
+    for remaining in (1..=len).rev() {
+        let Some((index, digit)) = (start..digits.len())
+            .filter(|&i| longest[i] >= remaining && prev.is_none_or(|p| digits[i] > p))
+            .map(|i| (i, digits[i]))
+            .rev()
+            .max_by_key(|&(_, digit)| digit)
+        else {
+            return Ok(None);
+        };
+        value = value * 10 + digit as usize;
+        start = index + 1;
+        prev = Some(digit);
+    }
+    Ok(Some(value))
+}
+
+/// Like [max_battery_of_length], but also returns the chosen digits exactly as selected, so that
+/// any leading zeros lost in the numeric value are preserved.
+fn max_battery_digits(
+    len: usize,
+    line: &str,
+    reverse: bool,
+    tie_break: TieBreak,
+) -> Result<(String, usize), ParseBatteryError> {
+    let battery = if reverse {
+        select_battery(len, &line.chars().rev().collect::<String>(), tie_break)
+    } else {
+        select_battery(len, line, tie_break)
+    }?;
+    let val = battery.parse().map_err(ParseBatteryError::ParseInt)?;
+    Ok((battery, val))
+}
+
+/// Selects the same digits as [max_battery_of_length], but rather than concatenating them,
+/// returns the sum of each chosen digit multiplied by the weight at its position. There must be
+/// exactly one weight per chosen digit.
+#[cfg_attr(not(test), allow(dead_code))]
+fn weighted_battery(len: usize, line: &str, weights: &[usize]) -> Result<usize, ParseBatteryError> {
+    if weights.len() != len {
+        return Err(ParseBatteryError::WeightCount);
+    }
+    select_battery(len, line, TieBreak::default())?
+        .chars()
+        .zip(weights)
+        .map(|(c, w)| {
+            c.to_digit(10)
+                .map(|d| d as usize * w)
+                .ok_or(ParseBatteryError::ParseBattery)
+        })
+        .sum()
+}
+
+fn extract_batteries(
+    r: impl std::io::BufRead,
+    reverse: bool,
+) -> impl Iterator<Item = (usize, usize)> {
     r.lines()
         .map_while(Result::ok)
         .filter(|line| !line.is_empty())
-        .map(|line| {
+        .map(move |line| {
             (
-                max_battery_of_length(2, &line).unwrap(),
-                max_battery_of_length(12, &line).unwrap(),
+                max_battery_of_length(2, &line, reverse, TieBreak::default()).unwrap(),
+                max_battery_of_length(12, &line, reverse, TieBreak::default()).unwrap(),
             )
         })
 }
 
+/// Tallies how often each digit is selected in the greatest battery of the given length, across
+/// every line. Lines without a valid battery of that length are skipped.
+#[cfg_attr(not(test), allow(dead_code))]
+fn selected_digit_frequencies(r: impl std::io::BufRead, len: usize) -> [usize; 10] {
+    let mut frequencies = [0; 10];
+    for line in r.lines().map_while(Result::ok) {
+        let Ok(indices) = select_battery_indices(len, &line, TieBreak::default()) else {
+            continue;
+        };
+        for i in indices {
+            if let Some(digit) = char::from(line.as_bytes()[i]).to_digit(10) {
+                frequencies[digit as usize] += 1;
+            }
+        }
+    }
+    frequencies
+}
+
+/// Maps each of the requested lengths to the line's greatest battery of that length, for every
+/// non-empty line. Lengths for which the line has no valid battery, such as those longer than the
+/// line, are omitted from that line's map, and repeated lengths are only computed once.
+#[cfg_attr(not(test), allow(dead_code))]
+fn extract_batteries_multi<'a>(
+    r: impl std::io::BufRead + 'a,
+    lengths: &'a [usize],
+) -> impl Iterator<Item = HashMap<usize, usize>> + 'a {
+    r.lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.is_empty())
+        .map(move |line| {
+            let mut batteries = HashMap::with_capacity(lengths.len());
+            for &len in lengths {
+                if batteries.contains_key(&len) {
+                    continue;
+                }
+                if let Ok(val) = max_battery_of_length(len, &line, false, TieBreak::default()) {
+                    batteries.insert(len, val);
+                }
+            }
+            batteries
+        })
+}
+
+/// Returns up to `k` distinct values of length-`len` subsequences of the line, in descending
+/// order.
+///
+/// Since all candidates have the same length, descending value is descending lexicographic
+/// order, so the search tries each next digit from 9 down to 0. Branching from the earliest
+/// feasible occurrence of a digit reaches every subsequence that a later occurrence could, so each
+/// distinct value is produced exactly once, in order, and the search stops after `k` are found.
+#[cfg_attr(not(test), allow(dead_code))]
+fn top_k_subsequences(len: usize, line: &str, k: usize) -> Vec<usize> {
+    fn search(
+        bytes: &[u8],
+        start: usize,
+        remaining: usize,
+        prefix: usize,
+        k: usize,
+        found: &mut Vec<usize>,
+    ) {
+        if remaining == 0 {
+            found.push(prefix);
+            return;
+        }
+        // only positions which leave enough digits after them are feasible
+        let window = &bytes[start..=(bytes.len() - remaining)];
+        for digit in (b'0'..=b'9').rev() {
+            if found.len() == k {
+                return;
+            }
+            if let Some(offset) = window.iter().position(|&b| b == digit) {
+                let prefix = prefix * 10 + (digit - b'0') as usize;
+                search(bytes, start + offset + 1, remaining - 1, prefix, k, found);
+            }
+        }
+    }
+
+    let mut found = Vec::with_capacity(k);
+    if k > 0 && line.len() >= len {
+        search(line.as_bytes(), 0, len, 0, k, &mut found);
+    }
+    found
+}
+
+/// Returns the index (among non-empty lines) and value of the line with the greatest battery of
+/// the given length, or [None] if no line has a valid battery. Ties are broken toward the earlier
+/// line.
+#[cfg_attr(not(test), allow(dead_code))]
+fn max_battery_line(r: impl std::io::BufRead, len: usize) -> Option<(usize, usize)> {
+    r.lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .filter_map(|(i, line)| {
+            max_battery_of_length(len, &line, false, TieBreak::default())
+                .ok()
+                .map(|val| (i, val))
+        })
+        .fold(None, |best, (i, val)| match best {
+            Some((_, best_val)) if best_val >= val => best,
+            _ => Some((i, val)),
+        })
+}
+
+/// Returns the digit with the longest consecutive run in the line, along with the length of that
+/// run. Ties are broken toward the larger digit. Non-digit characters break runs. If the line
+/// contains no digits, returns the sentinel `('\0', 0)`, which is recognizable by its zero length.
+fn longest_digit_run(line: &str) -> (char, usize) {
+    let mut longest = ('\0', 0);
+    let mut current = ('\0', 0);
+    for c in line.chars() {
+        if !c.is_ascii_digit() {
+            current = ('\0', 0);
+            continue;
+        }
+        current = if c == current.0 {
+            (c, current.1 + 1)
+        } else {
+            (c, 1)
+        };
+        if (current.1, current.0) > (longest.1, longest.0) {
+            longest = current;
+        }
+    }
+    longest
+}
+
 fn main() {
-    let (orig, static_friction): (usize, usize) = extract_batteries(std::io::stdin().lock())
-        .fold((0, 0), |acc, joltages| {
-            (acc.0 + joltages.0, acc.1 + joltages.1)
-        });
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--runs") {
+        for line in std::io::stdin()
+            .lines()
+            .map_while(Result::ok)
+            .filter(|line| !line.is_empty())
+        {
+            match longest_digit_run(&line) {
+                (_, 0) => println!("Longest run: no digits"),
+                (digit, length) => println!("Longest run: {length} of {digit}"),
+            }
+        }
+        return;
+    }
+    let (orig, static_friction): (usize, usize) = extract_batteries(
+        std::io::stdin().lock(),
+        args.iter().any(|arg| arg == "--reverse"),
+    )
+    .fold((0, 0), |acc, joltages| {
+        (acc.0 + joltages.0, acc.1 + joltages.1)
+    });
     println!("Sum of batteries: {orig}");
     println!("Sum of batteries with static friction: {static_friction}");
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{extract_batteries, max_battery_of_length};
+    use crate::{
+        ParseBatteryError, TieBreak, battery_digit_sum, earliest_max, extract_batteries,
+        extract_batteries_multi, longest_digit_run, max_battery_digits, max_battery_excluding,
+        max_battery_line, max_battery_of_length, max_increasing_battery, select_battery,
+        select_battery_indices, selected_digit_frequencies, top_k_subsequences, weighted_battery,
+    };
+    use std::collections::HashMap;
     use std::io::BufRead;
 
     const EXAMPLE_INPUT: &str = "
@@ -79,7 +421,10 @@ mod tests {
             .filter(|line| !line.is_empty())
             .zip(expected)
         {
-            assert_eq!(max_battery_of_length(2, &dbg!(line)), Ok(exp));
+            assert_eq!(
+                max_battery_of_length(2, &dbg!(line), false, TieBreak::default()),
+                Ok(exp)
+            );
         }
     }
 
@@ -92,14 +437,319 @@ mod tests {
             .filter(|line| !line.is_empty())
             .zip(expected)
         {
-            assert_eq!(max_battery_of_length(12, &dbg!(line)), Ok(exp));
+            assert_eq!(
+                max_battery_of_length(12, &dbg!(line), false, TieBreak::default()),
+                Ok(exp)
+            );
+        }
+    }
+
+    #[test]
+    fn test_max_battery_of_length_reverse() {
+        for line in EXAMPLE_INPUT
+            .lines()
+            .chain(LONGER_INPUT.lines())
+            .filter(|line| !line.is_empty())
+        {
+            let reversed: String = line.chars().rev().collect();
+            for len in [2, 12] {
+                assert_eq!(
+                    max_battery_of_length(len, line, true, TieBreak::default()),
+                    max_battery_of_length(len, &reversed, false, TieBreak::default())
+                );
+                assert_eq!(
+                    max_battery_of_length(len, &reversed, true, TieBreak::default()),
+                    max_battery_of_length(len, line, false, TieBreak::default())
+                );
+            }
+        }
+        assert_eq!(
+            max_battery_of_length(2, "811111111111119", true, TieBreak::default()),
+            Ok(98)
+        );
+        assert_eq!(
+            max_battery_of_length(3, "234234234234278", true, TieBreak::default()),
+            Ok(874)
+        );
+    }
+
+    #[test]
+    fn test_battery_digit_sum() {
+        // the selected batteries are 987654321111, 811111111119, 434234234278, 888911112111
+        let expected = vec![48, 27, 46, 42];
+        for (line, exp) in EXAMPLE_INPUT
+            .lines()
+            .filter(|line| !line.is_empty())
+            .zip(expected)
+        {
+            assert_eq!(battery_digit_sum(12, line), Ok(exp));
         }
+        assert_eq!(battery_digit_sum(3, "00070"), Ok(7));
+        assert_eq!(battery_digit_sum(3, "12"), Err(ParseBatteryError::TooShort));
+        assert_eq!(
+            battery_digit_sum(2, "9x"),
+            Err(ParseBatteryError::ParseBattery)
+        );
+    }
+
+    #[test]
+    fn test_max_battery_excluding() {
+        let dead = ['0', '1'];
+        assert_eq!(
+            max_battery_of_length(5, "818181911112111", false, TieBreak::default()),
+            Ok(92111)
+        );
+        assert_eq!(
+            max_battery_excluding(5, "818181911112111", &dead),
+            Ok(88892)
+        );
+        assert_eq!(max_battery_excluding(2, "811111111111119", &dead), Ok(89));
+        assert_eq!(
+            max_battery_excluding(3, "811111111111119", &dead),
+            Err(ParseBatteryError::TooShort)
+        );
+        assert_eq!(max_battery_excluding(2, "811111111111119", &[]), Ok(89));
+    }
+
+    #[test]
+    fn test_select_battery_indices() {
+        assert_eq!(
+            select_battery_indices(2, "811111111111119", TieBreak::Earliest),
+            Ok(vec![0, 14])
+        );
+        assert_eq!(
+            select_battery_indices(3, "818181911112111", TieBreak::Earliest),
+            Ok(vec![6, 11, 12])
+        );
+        assert_eq!(
+            select_battery_indices(3, "12", TieBreak::Earliest),
+            Err(ParseBatteryError::TooShort)
+        );
+    }
+
+    #[test]
+    fn test_tie_break() {
+        // Taking the second 9 first leaves only the 1 for the second digit.
+        assert_eq!(
+            select_battery_indices(2, "991", TieBreak::Latest),
+            Ok(vec![1, 2])
+        );
+        assert_eq!(
+            max_battery_of_length(2, "991", false, TieBreak::Earliest),
+            Ok(99)
+        );
+        assert_eq!(
+            max_battery_of_length(2, "991", false, TieBreak::Latest),
+            Ok(91)
+        );
+        assert_eq!(
+            max_battery_of_length(3, "818181911112111", false, TieBreak::Latest),
+            Ok(921)
+        );
+        // Taking the 8 at 2 rather than 0 forces the 1 at 5 in place of the third 8.
+        assert_eq!(
+            max_battery_of_length(12, "818181911112111", false, TieBreak::Earliest),
+            Ok(888911112111)
+        );
+        assert_eq!(
+            max_battery_of_length(12, "818181911112111", false, TieBreak::Latest),
+            Ok(881911112111)
+        );
+        for line in EXAMPLE_INPUT
+            .lines()
+            .chain(LONGER_INPUT.lines())
+            .filter(|line| !line.is_empty())
+        {
+            for len in [2, 12] {
+                let earliest = max_battery_of_length(len, line, false, TieBreak::Earliest);
+                assert_eq!(
+                    earliest,
+                    max_battery_of_length(len, line, false, TieBreak::default())
+                );
+                let latest = max_battery_of_length(len, line, false, TieBreak::Latest);
+                assert!(latest.unwrap() <= earliest.unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_selected_digit_frequencies() {
+        let input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        // the selected batteries are 987654321111, 811111111119, 434234234278, 888911112111
+        assert_eq!(
+            selected_digit_frequencies(input, 12),
+            [0, 21, 5, 4, 5, 1, 1, 2, 6, 3]
+        );
+        let input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        assert_eq!(
+            selected_digit_frequencies(input, 2),
+            [0, 0, 1, 0, 0, 0, 0, 1, 3, 3]
+        );
+    }
+
+    #[test]
+    fn test_max_increasing_battery() {
+        // The unconstrained battery would be 921.
+        assert_eq!(
+            max_battery_of_length(3, "818181911112111", false, TieBreak::default()),
+            Ok(921)
+        );
+        assert_eq!(max_increasing_battery(3, "818181911112111"), Ok(Some(189)));
+        // Taking the 4 first would leave only 7 and 8 to climb to.
+        assert_eq!(max_increasing_battery(4, "234234234234278"), Ok(Some(3478)));
+        assert_eq!(
+            max_increasing_battery(5, "234234234234278"),
+            Ok(Some(23478))
+        );
+        assert_eq!(max_increasing_battery(6, "234234234234278"), Ok(None));
+        assert_eq!(max_increasing_battery(2, "987654321111111"), Ok(None));
+        assert_eq!(max_increasing_battery(2, "811111111111119"), Ok(Some(89)));
+        assert_eq!(max_increasing_battery(1, "0"), Ok(Some(0)));
+        assert_eq!(
+            max_increasing_battery(3, "12"),
+            Err(ParseBatteryError::TooShort)
+        );
+        assert_eq!(
+            max_increasing_battery(2, "1x3"),
+            Err(ParseBatteryError::ParseBattery)
+        );
+    }
+
+    #[test]
+    fn test_max_battery_digits() {
+        assert_eq!(
+            max_battery_digits(2, "811111111111119", false, TieBreak::default()),
+            Ok(("89".to_string(), 89))
+        );
+        assert_eq!(
+            max_battery_digits(2, "00070", false, TieBreak::default()),
+            Ok(("70".to_string(), 70))
+        );
+        assert_eq!(
+            max_battery_digits(3, "00070", false, TieBreak::default()),
+            Ok(("070".to_string(), 70))
+        );
+        assert_eq!(
+            max_battery_digits(4, "00007", false, TieBreak::default()),
+            Ok(("0007".to_string(), 7))
+        );
+        assert_eq!(
+            max_battery_digits(2, "70000", true, TieBreak::default()),
+            Ok(("07".to_string(), 7))
+        );
+        assert_eq!(
+            max_battery_digits(3, "12", false, TieBreak::default()),
+            Err(ParseBatteryError::TooShort)
+        );
+        for line in EXAMPLE_INPUT.lines().filter(|line| !line.is_empty()) {
+            let (digits, val) = max_battery_digits(12, line, false, TieBreak::default()).unwrap();
+            assert_eq!(digits.len(), 12);
+            assert_eq!(
+                Ok(val),
+                max_battery_of_length(12, line, false, TieBreak::default())
+            );
+        }
+    }
+
+    /// The char-based scan which [select_battery] replaced.
+    fn select_battery_chars(len: usize, line: &str) -> Result<String, ParseBatteryError> {
+        if line.len() < len {
+            return Err(ParseBatteryError::TooShort);
+        }
+        let mut digits = String::new();
+        let mut prev_index: isize = -1; // a hack so we start looking at 0
+        for i in 0..len {
+            let start_index = (prev_index + 1) as usize;
+            let (greatest, neg_ind) = line
+                .get(start_index..(line.len() - len + 1 + i))
+                .ok_or(ParseBatteryError::ParseBattery)?
+                .chars()
+                .enumerate()
+                .map(|(ind, byt)| (byt, -(ind as isize)))
+                .max()
+                .ok_or(ParseBatteryError::ParseBattery)?;
+            digits.push(greatest);
+            prev_index = start_index as isize - neg_ind;
+        }
+        Ok(digits)
+    }
+
+    #[test]
+    fn test_select_battery_matches_chars() {
+        for line in EXAMPLE_INPUT
+            .lines()
+            .chain(LONGER_INPUT.lines())
+            .chain(["9999", "19191", "0000", "1", ""])
+        {
+            for len in [0, 1, 2, 3, 12, 100] {
+                assert_eq!(
+                    select_battery(len, line, TieBreak::default()),
+                    select_battery_chars(len, line),
+                    "{len} of {line}"
+                );
+            }
+        }
+        // A trailing non-digit must not be skipped just because a `9` was found first.
+        for line in ["9a", "abc9", "98 7"] {
+            for len in [1, 2] {
+                assert_eq!(
+                    select_battery(len, line, TieBreak::default()),
+                    Err(ParseBatteryError::ParseBattery),
+                    "{len} of {line}"
+                );
+            }
+        }
+        assert_eq!(
+            max_battery_of_length(1, "9a", false, TieBreak::default()),
+            Err(ParseBatteryError::ParseBattery)
+        );
+        assert_eq!(earliest_max(b"2939"), Some((1, b'9')));
+        assert_eq!(earliest_max(b"38183"), Some((1, b'8')));
+        assert_eq!(earliest_max(b""), None);
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture` to see the timing.
+    #[test]
+    #[ignore]
+    fn bench_select_battery() {
+        let line: String = LONGER_INPUT
+            .lines()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .concat()
+            .repeat(200);
+        let select_bytes = |len, line: &str| select_battery(len, line, TieBreak::default());
+        for (name, select) in [
+            ("bytes", select_bytes as fn(usize, &str) -> _),
+            ("chars", select_battery_chars),
+        ] {
+            let start = std::time::Instant::now();
+            let battery = select(1000, &line).unwrap();
+            println!("select_battery {name}: {:?}", start.elapsed());
+            assert_eq!(battery.len(), 1000);
+        }
+    }
+
+    #[test]
+    fn test_weighted_battery() {
+        let line = "818181911112111";
+        assert_eq!(weighted_battery(3, line, &[100, 10, 1]), Ok(921));
+        assert_eq!(weighted_battery(3, line, &[3, 2, 1]), Ok(27 + 4 + 1));
+        assert_eq!(weighted_battery(2, line, &[1, 1]), Ok(9 + 2));
+        assert_eq!(
+            weighted_battery(3, line, &[1, 1]),
+            Err(ParseBatteryError::WeightCount)
+        );
+        assert_eq!(
+            weighted_battery(3, "12", &[1, 1, 1]),
+            Err(ParseBatteryError::TooShort)
+        );
     }
 
     #[test]
     fn test_extract_batteries() {
         let input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
-        let result: Vec<(usize, usize)> = extract_batteries(input).collect();
+        let result: Vec<(usize, usize)> = extract_batteries(input, false).collect();
         assert_eq!(
             result,
             vec![
@@ -111,10 +761,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_extract_batteries_multi() {
+        let input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let result: Vec<_> = extract_batteries_multi(input, &[2, 5, 12]).collect();
+        assert_eq!(
+            result,
+            vec![
+                HashMap::from([(2, 98), (5, 98765), (12, 987654321111)]),
+                HashMap::from([(2, 89), (5, 81119), (12, 811111111119)]),
+                HashMap::from([(2, 78), (5, 44478), (12, 434234234278)]),
+                HashMap::from([(2, 92), (5, 92111), (12, 888911112111)]),
+            ]
+        );
+
+        let input = std::io::BufReader::new("12345\n123".as_bytes());
+        let result: Vec<_> = extract_batteries_multi(input, &[2, 4, 2]).collect();
+        assert_eq!(
+            result,
+            vec![
+                HashMap::from([(2, 45), (4, 2345)]),
+                HashMap::from([(2, 23)])
+            ]
+        );
+    }
+
     #[test]
     fn test_extract_batteries_longer_input() {
         let input = std::io::BufReader::new(LONGER_INPUT.as_bytes());
-        let result: Vec<usize> = extract_batteries(input).map(|(x, _)| x).collect();
+        let result: Vec<usize> = extract_batteries(input, false).map(|(x, _)| x).collect();
         assert_eq!(result, vec![87, 97, 99, 99, 66]);
     }
+
+    #[test]
+    fn test_top_k_subsequences() {
+        // all length 2 subsequences of 2413 are 24, 21, 23, 41, 43, 13
+        assert_eq!(top_k_subsequences(2, "2413", 3), vec![43, 41, 24]);
+        assert_eq!(
+            top_k_subsequences(2, "2413", 10),
+            vec![43, 41, 24, 23, 21, 13]
+        );
+        assert_eq!(top_k_subsequences(2, "1111", 3), vec![11]);
+        assert_eq!(top_k_subsequences(3, "2413", 0), vec![]);
+        assert_eq!(top_k_subsequences(5, "2413", 3), vec![]);
+        for line in EXAMPLE_INPUT.lines().filter(|line| !line.is_empty()) {
+            let top = top_k_subsequences(12, line, 3);
+            assert_eq!(
+                Ok(top[0]),
+                max_battery_of_length(12, line, false, TieBreak::default())
+            );
+            assert!(top.windows(2).all(|w| w[0] > w[1]));
+        }
+    }
+
+    #[test]
+    fn test_max_battery_line() {
+        let input = std::io::BufReader::new(LONGER_INPUT.as_bytes());
+        assert_eq!(max_battery_line(input, 2), Some((2, 99)));
+        let input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        assert_eq!(max_battery_line(input, 12), Some((0, 987654321111)));
+        let input = std::io::BufReader::new("".as_bytes());
+        assert_eq!(max_battery_line(input, 2), None);
+    }
+
+    #[test]
+    fn test_longest_digit_run() {
+        assert_eq!(longest_digit_run("1112222333"), ('2', 4));
+        assert_eq!(longest_digit_run("111222"), ('2', 3));
+        assert_eq!(longest_digit_run("222111"), ('2', 3));
+        assert_eq!(longest_digit_run("77777"), ('7', 5));
+        assert_eq!(longest_digit_run("99a99"), ('9', 2));
+        assert_eq!(longest_digit_run("987654321111111"), ('1', 7));
+    }
+
+    #[test]
+    fn test_longest_digit_run_no_digits() {
+        assert_eq!(longest_digit_run(""), ('\0', 0));
+        assert_eq!(longest_digit_run("abc"), ('\0', 0));
+    }
 }
@@ -1,12 +1,36 @@
 use std::mem;
+use std::ops::{Index, IndexMut};
+
+/// Returns the weight of the roll represented by the given character, if any. An `@` is a roll of
+/// weight 1, and a digit from `1` to `9` is a roll of that weight.
+fn roll_weight(c: char) -> Option<usize> {
+    match c {
+        '@' => Some(1),
+        '1'..='9' => c.to_digit(10).map(|d| d as usize),
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseSparseError {
+    ParseDimensions,
+    ParseRoll(String),
+    OutOfBounds(usize, usize),
+}
+
+/// The summed weight of neighboring rolls below which a roll is movable.
+const MOVABLE_THRESHOLD: usize = 4;
 
 // At each position with roll, look at the current count which has been placed on the position,
 // then look to the right and one row down (three touching positions), and add the total number of
 // rolls together. Also, add 1 to each of those positions which has a roll.
 
+/// The `neighbors` count is the summed weight of the neighboring rolls, which is simply the number
+/// of neighboring rolls when every roll has weight 1.
 #[derive(Clone)]
 struct Entry {
     is_roll: bool,
+    weight: usize,
     neighbors: usize,
 }
 
@@ -14,37 +38,44 @@ impl Entry {
     fn new() -> Entry {
         Entry {
             is_roll: false,
+            weight: 0,
             neighbors: 0,
         }
     }
 
-    fn new_with_roll(is_roll: bool) -> Entry {
+    fn new_with_weight(weight: Option<usize>) -> Entry {
         Entry {
-            is_roll,
+            is_roll: weight.is_some(),
+            weight: weight.unwrap_or(0),
             neighbors: 0,
         }
     }
 
     fn set_roll(&mut self) {
+        self.set_weighted_roll(1);
+    }
+
+    fn set_weighted_roll(&mut self, weight: usize) {
         self.is_roll = true;
+        self.weight = weight;
     }
 
-    fn unset_roll(&mut self) {
+    /// Removes the roll, returning its weight.
+    fn unset_roll(&mut self) -> usize {
         self.is_roll = false;
+        mem::take(&mut self.weight)
     }
 
-    fn inc_neighbors(&mut self) {
-        self.neighbors += 1;
+    fn inc_neighbors(&mut self, weight: usize) {
+        self.neighbors += weight;
     }
 
-    fn dec_neighbors(&mut self) {
-        self.is_roll;
-        self.neighbors;
-        self.neighbors -= 1;
+    fn dec_neighbors(&mut self, weight: usize) {
+        self.neighbors -= weight;
     }
 
     fn is_movable(&self) -> bool {
-        self.is_roll && self.neighbors < 4
+        self.is_roll && self.neighbors < MOVABLE_THRESHOLD
     }
 }
 
@@ -81,25 +112,24 @@ impl RowRememberer {
             self.curr_row = vec![Entry::new(); self.width];
         }
         let mut next = vec![Entry::new(); self.width];
-        for index in row
+        for (index, weight) in row
             .chars()
             .enumerate()
-            .filter(|(_, c)| *c == '@')
-            .map(|(i, _)| i)
+            .filter_map(|(i, c)| roll_weight(c).map(|w| (i, w)))
         {
             if let Some(left) = index.checked_sub(1) {
-                self.prev_row[left].inc_neighbors();
-                self.curr_row[left].inc_neighbors();
-                next[left].inc_neighbors();
+                self.prev_row[left].inc_neighbors(weight);
+                self.curr_row[left].inc_neighbors(weight);
+                next[left].inc_neighbors(weight);
             }
-            self.curr_row[index].set_roll();
-            self.prev_row[index].inc_neighbors();
-            next[index].inc_neighbors();
+            self.curr_row[index].set_weighted_roll(weight);
+            self.prev_row[index].inc_neighbors(weight);
+            next[index].inc_neighbors(weight);
             let right = index + 1;
             if right < self.width {
-                self.prev_row[right].inc_neighbors();
-                self.curr_row[right].inc_neighbors();
-                next[right].inc_neighbors();
+                self.prev_row[right].inc_neighbors(weight);
+                self.curr_row[right].inc_neighbors(weight);
+                next[right].inc_neighbors(weight);
             }
         }
         let prev_count = self.tally_prev_row();
@@ -114,34 +144,186 @@ impl RowRememberer {
     }
 }
 
+/// The entries of a [Room], indexed by `(row, col)`. Each row is stored in its own [Vec], unless
+/// the `flat` feature is enabled, in which case every entry is stored in a single row-major [Vec]
+/// for better locality in the sweeps.
+#[cfg(not(feature = "flat"))]
+#[derive(Clone)]
+struct Cells(Vec<Vec<Entry>>);
+
+#[cfg(not(feature = "flat"))]
+impl Cells {
+    fn from_rows(rows: impl IntoIterator<Item = Vec<Entry>>) -> Self {
+        Cells(rows.into_iter().collect())
+    }
+
+    fn num_rows(&self) -> usize {
+        self.0.len()
+    }
+
+    fn get(&self, r: usize, c: usize) -> Option<&Entry> {
+        self.0.get(r).and_then(|row| row.get(c))
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[Entry]> {
+        self.0.iter().map(Vec::as_slice)
+    }
+}
+
+#[cfg(not(feature = "flat"))]
+impl Index<(usize, usize)> for Cells {
+    type Output = Entry;
+
+    fn index(&self, (r, c): (usize, usize)) -> &Entry {
+        &self.0[r][c]
+    }
+}
+
+#[cfg(not(feature = "flat"))]
+impl IndexMut<(usize, usize)> for Cells {
+    fn index_mut(&mut self, (r, c): (usize, usize)) -> &mut Entry {
+        &mut self.0[r][c]
+    }
+}
+
+#[cfg(feature = "flat")]
+#[derive(Clone)]
+struct Cells {
+    width: usize,
+    entries: Vec<Entry>,
+}
+
+#[cfg(feature = "flat")]
+impl Cells {
+    /// Assumes all rows have the same width. The rows are appended as they arrive, so that the
+    /// whole grid is never held in both representations at once.
+    fn from_rows(rows: impl IntoIterator<Item = Vec<Entry>>) -> Self {
+        let mut rows = rows.into_iter().peekable();
+        let width = rows.peek().map_or(0, Vec::len);
+        let mut entries = Vec::new();
+        for row in rows {
+            debug_assert_eq!(row.len(), width);
+            entries.extend(row);
+        }
+        Cells { width, entries }
+    }
+
+    fn num_rows(&self) -> usize {
+        self.entries.len() / self.width.max(1)
+    }
+
+    fn get(&self, r: usize, c: usize) -> Option<&Entry> {
+        if c >= self.width {
+            return None;
+        }
+        self.entries.get(r * self.width + c)
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[Entry]> {
+        // a zero width room has no entries, but chunks must be non-empty
+        self.entries.chunks(self.width.max(1))
+    }
+}
+
+#[cfg(feature = "flat")]
+impl Index<(usize, usize)> for Cells {
+    type Output = Entry;
+
+    fn index(&self, (r, c): (usize, usize)) -> &Entry {
+        debug_assert!(c < self.width);
+        &self.entries[r * self.width + c]
+    }
+}
+
+#[cfg(feature = "flat")]
+impl IndexMut<(usize, usize)> for Cells {
+    fn index_mut(&mut self, (r, c): (usize, usize)) -> &mut Entry {
+        debug_assert!(c < self.width);
+        &mut self.entries[r * self.width + c]
+    }
+}
+
+impl Cells {
+    fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.rows().flatten()
+    }
+}
+
+#[derive(Clone)]
 struct Room {
     height: usize,
     width: usize,
-    rows: Vec<Vec<Entry>>,
+    cells: Cells,
 }
 
 impl Room {
     fn from(r: impl std::io::BufRead) -> Room {
-        let rows: Vec<Vec<Entry>> = r
-            .lines()
-            .map_while(Result::ok)
-            .filter(|line| !line.is_empty())
-            .map(|line| {
-                line.chars()
-                    .map(|c| Entry::new_with_roll(c == '@'))
-                    .collect::<Vec<Entry>>()
-            })
-            .collect();
-        let height = rows.len();
-        let width = rows.last().unwrap().len();
+        let cells = Cells::from_rows(
+            r.lines()
+                .map_while(Result::ok)
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    line.chars()
+                        .map(|c| Entry::new_with_weight(roll_weight(c)))
+                        .collect::<Vec<Entry>>()
+                }),
+        );
+        let height = cells.num_rows();
+        let width = cells.rows().last().unwrap().len();
         Room {
             height,
             width,
-            rows,
+            cells,
         }
         .prepare()
     }
 
+    /// Builds a room of the given dimensions with rolls at the given `(row, col)` coordinates.
+    /// Returns [ParseSparseError::OutOfBounds] if any coordinate lies outside the room.
+    fn from_coords(
+        width: usize,
+        height: usize,
+        rolls: &[(usize, usize)],
+    ) -> Result<Room, ParseSparseError> {
+        let mut rows = vec![vec![Entry::new(); width]; height];
+        for &(r, c) in rolls {
+            rows.get_mut(r)
+                .and_then(|row| row.get_mut(c))
+                .ok_or(ParseSparseError::OutOfBounds(r, c))?
+                .set_roll();
+        }
+        Ok(Room {
+            height,
+            width,
+            cells: Cells::from_rows(rows),
+        }
+        .prepare())
+    }
+
+    /// Builds a room from a sparse description, whose first non-empty line is the `width height`
+    /// of the room, followed by one `row col` line per roll. Returns an error carrying the first
+    /// malformed roll line, or the first roll which lies outside the room.
+    fn from_sparse(r: impl std::io::BufRead) -> Result<Room, ParseSparseError> {
+        let mut lines = r
+            .lines()
+            .map_while(Result::ok)
+            .filter(|line| !line.trim().is_empty());
+        let (width, height) = lines
+            .next()
+            .as_deref()
+            .and_then(parse_pair)
+            .ok_or(ParseSparseError::ParseDimensions)?;
+        let rolls = lines
+            .map(|line| parse_pair(&line).ok_or(ParseSparseError::ParseRoll(line)))
+            .collect::<Result<Vec<(usize, usize)>, _>>()?;
+        Room::from_coords(width, height, &rolls)
+    }
+
+    /// Returns the number of rolls which are currently movable.
+    fn movable_count(&self) -> usize {
+        self.cells.entries().filter(|e| e.is_movable()).count()
+    }
+
     // This should probably be optimized more...
     fn find_neighbors(&self, r: usize, c: usize, neighbors: &mut Vec<(usize, usize)>) {
         neighbors.clear();
@@ -180,14 +362,15 @@ impl Room {
         let mut neighbors: Vec<(usize, usize)> = Vec::with_capacity(8);
         for i in 0..self.height {
             for j in 0..self.width {
-                if !self.rows[i][j].is_roll {
+                if !self.cells[(i, j)].is_roll {
                     continue;
                 }
+                let weight = self.cells[(i, j)].weight;
                 self.find_neighbors(i, j, &mut neighbors);
                 for (x, y) in &neighbors {
                     // Probably faster to use checked getter methods rather than pre-check coords
                     // and then do checked indexing
-                    self.rows[*x][*y].inc_neighbors();
+                    self.cells[(*x, *y)].inc_neighbors(weight);
                 }
             }
         }
@@ -199,23 +382,267 @@ impl Room {
     /// as the result of the removal of previous rolls during the sweep, and thus be itself removed
     /// during that sweep.
     fn sweep(&mut self) -> usize {
+        self.sweep_with(|_, _| {})
+    }
+
+    /// Performs a [Room::sweep], calling `on_remove` with the coordinates of each roll as it is
+    /// removed.
+    fn sweep_with(&mut self, mut on_remove: impl FnMut(usize, usize)) -> usize {
+        self.sweep_limited(usize::MAX, |r, c, _| on_remove(r, c))
+    }
+
+    /// Performs a [Room::sweep], returning the `(row, col, weight)` of the removed rolls in the
+    /// order they were removed, which can be passed to [Room::restore] to undo the sweep.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn sweep_tracked(&mut self) -> Vec<(usize, usize, usize)> {
+        let mut removed = Vec::new();
+        self.sweep_limited(usize::MAX, |r, c, weight| removed.push((r, c, weight)));
+        removed
+    }
+
+    /// Re-adds rolls of the given weights at the given coordinates, updating the neighbor counts
+    /// around each. Cells which already hold a roll are left alone.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn restore(&mut self, rolls: &[(usize, usize, usize)]) {
+        let mut neighbors: Vec<(usize, usize)> = Vec::with_capacity(8);
+        for &(r, c, weight) in rolls {
+            if self.cells[(r, c)].is_roll {
+                continue;
+            }
+            self.cells[(r, c)].set_weighted_roll(weight);
+            self.find_neighbors(r, c, &mut neighbors);
+            for (x, y) in &neighbors {
+                self.cells[(*x, *y)].inc_neighbors(weight);
+            }
+        }
+    }
+
+    /// Performs a [Room::sweep_with], but stops as soon as `limit` rolls have been removed. The
+    /// weight of each removed roll is also passed to `on_remove`.
+    fn sweep_limited(
+        &mut self,
+        limit: usize,
+        mut on_remove: impl FnMut(usize, usize, usize),
+    ) -> usize {
         let mut neighbors: Vec<(usize, usize)> = Vec::with_capacity(8);
         let mut count = 0;
         for i in 0..self.height {
             for j in 0..self.width {
-                if !self.rows[i][j].is_movable() {
+                if count == limit {
+                    return count;
+                }
+                if !self.cells[(i, j)].is_movable() {
                     continue;
                 }
                 count += 1;
-                self.rows[i][j].unset_roll();
+                let weight = self.cells[(i, j)].unset_roll();
+                on_remove(i, j, weight);
                 self.find_neighbors(i, j, &mut neighbors);
                 for (x, y) in &neighbors {
-                    self.rows[*x][*y].dec_neighbors();
+                    self.cells[(*x, *y)].dec_neighbors(weight);
+                }
+            }
+        }
+        count
+    }
+
+    /// Greedily removes rolls across as many sweeps as needed, but stops once `n` rolls have been
+    /// removed, even partway through a sweep. Returns the number of rolls actually removed, which
+    /// is less than `n` only if the room stabilized first.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn remove_up_to(&mut self, n: usize) -> usize {
+        let mut removed = 0;
+        while removed < n {
+            let count = self.sweep_limited(n - removed, |_, _, _| {});
+            if count == 0 {
+                break;
+            }
+            removed += count;
+        }
+        removed
+    }
+
+    /// Returns the `(row, col)` coordinates of every roll currently in the room, in row-major order.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn roll_positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells.rows().enumerate().flat_map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.is_roll)
+                .map(move |(j, _)| (i, j))
+        })
+    }
+
+    /// Returns the number of movable rolls in the top-left, top-right, bottom-left, and
+    /// bottom-right quadrants of the room, in that order. The room is split at `height / 2` and
+    /// `width / 2`, rounding down, so for odd dimensions the middle row belongs to the bottom
+    /// quadrants and the middle column belongs to the right quadrants.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn quadrant_movable_counts(&self) -> [usize; 4] {
+        let (mid_row, mid_col) = (self.height / 2, self.width / 2);
+        let mut counts = [0; 4];
+        for (i, row) in self.cells.rows().enumerate() {
+            for (j, entry) in row.iter().enumerate() {
+                if entry.is_movable() {
+                    counts[2 * usize::from(i >= mid_row) + usize::from(j >= mid_col)] += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// Returns the number of clusters of rolls, where rolls touching in any of the eight
+    /// directions belong to the same cluster, regardless of whether they are movable.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn count_components(&self) -> usize {
+        let mut visited = vec![vec![false; self.width]; self.height];
+        let mut neighbors: Vec<(usize, usize)> = Vec::with_capacity(8);
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        let mut count = 0;
+        for (i, j) in self.roll_positions() {
+            if visited[i][j] {
+                continue;
+            }
+            count += 1;
+            visited[i][j] = true;
+            stack.push((i, j));
+            while let Some((r, c)) = stack.pop() {
+                self.find_neighbors(r, c, &mut neighbors);
+                for &(x, y) in &neighbors {
+                    if self.cells[(x, y)].is_roll && !visited[x][y] {
+                        visited[x][y] = true;
+                        stack.push((x, y));
+                    }
                 }
             }
         }
         count
     }
+
+    /// Returns the mean of the neighbor counts of the rolls currently in the room, or 0.0 if there
+    /// are no rolls.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn mean_roll_neighbors(&self) -> f64 {
+        let (total, rolls) = self
+            .cells
+            .entries()
+            .filter(|e| e.is_roll)
+            .fold((0, 0), |(total, rolls), e| (total + e.neighbors, rolls + 1));
+        if rolls == 0 {
+            return 0.0;
+        }
+        total as f64 / rolls as f64
+    }
+
+    /// Returns the `(row, col, count)` of the 3x3 window containing the most rolls, where `(row,
+    /// col)` is its top-left corner. Only windows which fit entirely within the room are
+    /// considered, and ties are broken toward the earliest window in row-major order. Returns
+    /// [None] if the room is smaller than 3x3.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn densest_region(&self) -> Option<(usize, usize, usize)> {
+        const SIZE: usize = 3;
+        if self.height < SIZE || self.width < SIZE {
+            return None;
+        }
+        // the number of rolls in each column of the current band of rows
+        let mut column_counts = vec![0; self.width];
+        let mut best: Option<(usize, usize, usize)> = None;
+        for bottom in 0..self.height {
+            for (j, count) in column_counts.iter_mut().enumerate() {
+                *count += usize::from(self.cells[(bottom, j)].is_roll);
+                if bottom >= SIZE {
+                    *count -= usize::from(self.cells[(bottom - SIZE, j)].is_roll);
+                }
+            }
+            let Some(top) = (bottom + 1).checked_sub(SIZE) else {
+                continue;
+            };
+            let mut window: usize = column_counts[..SIZE].iter().sum();
+            for left in 0..=(self.width - SIZE) {
+                if left > 0 {
+                    window += column_counts[left + SIZE - 1];
+                    window -= column_counts[left - 1];
+                }
+                if best.is_none_or(|(_, _, most)| window > most) {
+                    best = Some((top, left, window));
+                }
+            }
+        }
+        best
+    }
+
+    /// Returns true if any roll in the room is currently movable, without changing any state.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn has_movable(&self) -> bool {
+        self.cells.entries().any(Entry::is_movable)
+    }
+
+    /// Returns true if the roll at `(r, c)` is removed at some point before the room stabilizes.
+    /// Simulates sweeps on a copy of the room, stopping after the sweep which removes it.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn will_move(&self, r: usize, c: usize) -> bool {
+        if !self.cells.get(r, c).is_some_and(|e| e.is_roll) {
+            return false;
+        }
+        let mut room = self.clone();
+        loop {
+            let mut moved = false;
+            if room.sweep_with(|i, j| moved |= (i, j) == (r, c)) == 0 {
+                return false;
+            }
+            if moved {
+                return true;
+            }
+        }
+    }
+
+    /// Returns a hash of the set of `(row, col)` positions of the rolls currently in the room, for
+    /// comparing the shapes which different rooms stabilize to. The positions are hashed in
+    /// row-major order with FNV-1a, so the fingerprint depends only on the set of positions and
+    /// is stable across runs and platforms.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn state_fingerprint(&self) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+        self.roll_positions()
+            .flat_map(|(r, c)| {
+                (r as u64)
+                    .to_le_bytes()
+                    .into_iter()
+                    .chain((c as u64).to_le_bytes())
+            })
+            .fold(FNV_OFFSET, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// Renders the room with `.` for empty positions, `o` for rolls which cannot be moved, and `O`
+    /// for rolls which can be moved. Calling this on a freshly constructed [Room] renders its
+    /// initial state.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn render_movability(&self) -> String {
+        let mut rendered = String::with_capacity((self.width + 1) * self.height);
+        for row in self.cells.rows() {
+            for entry in row {
+                rendered.push(match (entry.is_roll, entry.is_movable()) {
+                    (false, _) => '.',
+                    (true, false) => 'o',
+                    (true, true) => 'O',
+                });
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+}
+
+/// Parses a line of exactly two whitespace-separated numbers, as used by [Room::from_sparse].
+fn parse_pair(line: &str) -> Option<(usize, usize)> {
+    let mut nums = line.split_whitespace().map(str::parse);
+    match (nums.next(), nums.next(), nums.next()) {
+        (Some(Ok(a)), Some(Ok(b)), None) => Some((a, b)),
+        _ => None,
+    }
 }
 
 fn count_initially_movable(r: impl std::io::BufRead) -> usize {
@@ -229,16 +656,61 @@ fn count_initially_movable(r: impl std::io::BufRead) -> usize {
 }
 
 fn count_eventually_movable(r: impl std::io::BufRead) -> usize {
+    sweep_profile(r).into_iter().sum()
+}
+
+/// Returns the number of rolls removed by each sweep, in order, until the room stabilizes. The
+/// final sweep which removes nothing is not included, so a room with no movable rolls yields an
+/// empty profile.
+fn sweep_profile(r: impl std::io::BufRead) -> Vec<usize> {
     let mut room = Room::from(r);
-    let mut total_moved = 0;
+    let mut profile = Vec::new();
     loop {
         let count = room.sweep();
         if count == 0 {
             break;
         }
-        total_moved += count;
+        profile.push(count);
     }
-    total_moved
+    profile
+}
+
+/// Returns the `(row, col, sweep)` of every roll in the order it is removed, where `sweep` is the
+/// 0-based index of the sweep which removed it.
+#[cfg_attr(not(test), allow(dead_code))]
+fn removal_order(r: impl std::io::BufRead) -> Vec<(usize, usize, usize)> {
+    let mut room = Room::from(r);
+    let mut order = Vec::new();
+    for sweep in 0.. {
+        if room.sweep_with(|i, j| order.push((i, j, sweep))) == 0 {
+            break;
+        }
+    }
+    order
+}
+
+/// Returns the `(row, col)` of every roll which was not movable initially but was removed before
+/// the room stabilized, in the order they were removed.
+#[cfg_attr(not(test), allow(dead_code))]
+fn cascade_cells(r: impl std::io::BufRead) -> Vec<(usize, usize)> {
+    let mut room = Room::from(r);
+    let initially_movable: Vec<Vec<bool>> = room
+        .cells
+        .rows()
+        .map(|row| row.iter().map(Entry::is_movable).collect())
+        .collect();
+    let mut cascade = Vec::new();
+    loop {
+        let removed = room.sweep_with(|i, j| {
+            if !initially_movable[i][j] {
+                cascade.push((i, j));
+            }
+        });
+        if removed == 0 {
+            break;
+        }
+    }
+    cascade
 }
 
 fn main() {
@@ -252,6 +724,26 @@ fn main() {
                 acc.push('\n');
                 acc
             });
+    // A dense row never contains whitespace, so a first line of two numbers must be dimensions.
+    let is_sparse = input
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .and_then(parse_pair)
+        .is_some();
+    if is_sparse {
+        let mut room = match Room::from_sparse(std::io::BufReader::new(input.as_bytes())) {
+            Ok(room) => room,
+            Err(e) => {
+                eprintln!("Error parsing sparse room: {e:?}");
+                return;
+            }
+        };
+        println!("Initially movable rolls: {}", room.movable_count());
+        let eventually_movable: usize =
+            std::iter::from_fn(|| Some(room.sweep()).filter(|count| *count > 0)).sum();
+        println!("Eventually movable rolls: {eventually_movable}");
+        return;
+    }
     let initially_movable = count_initially_movable(std::io::BufReader::new(input.as_bytes()));
     println!("Initially movable rolls: {initially_movable}");
     let eventually_movable = count_eventually_movable(std::io::BufReader::new(input.as_bytes()));
@@ -279,6 +771,39 @@ mod tests {
         assert_eq!(result, 13);
     }
 
+    /// Run with `cargo test --release -- --ignored --nocapture` to see the timing, with and without
+    /// `--features flat` to compare the representations.
+    #[test]
+    #[ignore]
+    fn bench_count_eventually_movable() {
+        const N: usize = 2000;
+        let grid: String = (0..N)
+            .map(|i| {
+                (0..N)
+                    .map(|j| {
+                        if (i * 7 + j * 13 + i * j) % 5 == 0 {
+                            '.'
+                        } else {
+                            '@'
+                        }
+                    })
+                    .chain(['\n'])
+                    .collect::<String>()
+            })
+            .collect();
+        let representation = if cfg!(feature = "flat") {
+            "flat"
+        } else {
+            "nested"
+        };
+        let start = std::time::Instant::now();
+        let removed = super::count_eventually_movable(std::io::BufReader::new(grid.as_bytes()));
+        println!(
+            "count_eventually_movable {representation} {N}x{N}: {removed} removed in {:?}",
+            start.elapsed()
+        );
+    }
+
     #[test]
     fn test_count_eventually_movable() {
         let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
@@ -286,6 +811,370 @@ mod tests {
         assert_eq!(result, 43);
     }
 
+    #[test]
+    fn test_sweep_profile() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let result = super::sweep_profile(test_input);
+        assert_eq!(result, vec![30, 9, 4]);
+        assert_eq!(result.iter().sum::<usize>(), 43);
+    }
+
+    #[test]
+    fn test_remove_up_to() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let room = super::Room::from(test_input);
+        // 30 rolls are removed in the first sweep, so 35 stops partway through the second.
+        for n in [0, 5, 35] {
+            let mut partial = room.clone();
+            assert_eq!(partial.remove_up_to(n), n);
+            assert_eq!(partial.roll_positions().count(), 71 - n);
+            let positions: Vec<(usize, usize)> = partial.roll_positions().collect();
+            let recomputed =
+                super::Room::from_coords(partial.width, partial.height, &positions).unwrap();
+            for (row, expected) in partial.cells.rows().zip(recomputed.cells.rows()) {
+                for (entry, exp) in row.iter().zip(expected) {
+                    assert_eq!(entry.neighbors, exp.neighbors);
+                }
+            }
+        }
+        let mut all = room.clone();
+        assert_eq!(all.remove_up_to(100), 43);
+        assert!(!all.has_movable());
+    }
+
+    fn assert_same_cells(room: &super::Room, expected: &super::Room) {
+        for (row, expected) in room.cells.rows().zip(expected.cells.rows()) {
+            for (entry, exp) in row.iter().zip(expected) {
+                assert_eq!(
+                    (entry.is_roll, entry.weight, entry.neighbors),
+                    (exp.is_roll, exp.weight, exp.neighbors)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sweep_tracked_and_restore() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let original = super::Room::from(test_input);
+        let mut room = original.clone();
+        let removed = room.sweep_tracked();
+        assert_eq!(removed.len(), 30);
+        assert_eq!(removed[0], (0, 2, 1));
+        assert_ne!(room.state_fingerprint(), original.state_fingerprint());
+
+        room.restore(&removed);
+        assert_eq!(room.state_fingerprint(), original.state_fingerprint());
+        assert_eq!(room.movable_count(), 13);
+        assert_same_cells(&room, &original);
+        assert_eq!(room.sweep_tracked(), removed);
+    }
+
+    #[test]
+    fn test_restore_weighted() {
+        let test_input = std::io::BufReader::new("3@9\n...\n".as_bytes());
+        let original = super::Room::from(test_input);
+        let mut room = original.clone();
+        let removed = room.sweep_tracked();
+        // The middle roll is held by the weight 9 roll when it is reached.
+        assert_eq!(removed, vec![(0, 0, 3), (0, 2, 9)]);
+        assert_eq!(room.cells[(0, 1)].neighbors, 0);
+        room.restore(&removed);
+        assert_eq!(room.cells[(0, 1)].neighbors, 12);
+        assert_same_cells(&room, &original);
+        assert_eq!(room.sweep_tracked(), removed);
+    }
+
+    #[test]
+    fn test_roll_positions() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let mut room = super::Room::from(test_input);
+        let manual_count = EXAMPLE_INPUT.chars().filter(|c| *c == '@').count();
+        assert_eq!(room.roll_positions().count(), manual_count);
+        assert_eq!(room.roll_positions().next(), Some((0, 2)));
+        let removed = room.sweep();
+        assert_eq!(room.roll_positions().count(), manual_count - removed);
+        assert!(
+            room.roll_positions()
+                .all(|(i, j)| room.cells[(i, j)].is_roll)
+        );
+    }
+
+    #[test]
+    fn test_quadrant_movable_counts() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let room = super::Room::from(test_input);
+        let counts = room.quadrant_movable_counts();
+        assert_eq!(counts, [4, 5, 3, 1]);
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        assert_eq!(
+            counts.iter().sum::<usize>(),
+            super::count_initially_movable(test_input)
+        );
+
+        // The middle row and column of odd dimensions belong to the bottom and right quadrants.
+        let test_input = std::io::BufReader::new("@@@\n@@@\n.@.\n".as_bytes());
+        let room = super::Room::from(test_input);
+        assert_eq!(room.quadrant_movable_counts(), [1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_mean_roll_neighbors() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let room = super::Room::from(test_input);
+        // 352 neighbor counts over 71 rolls
+        assert!((room.mean_roll_neighbors() - 4.9577).abs() < 1e-4);
+
+        let test_input = std::io::BufReader::new("@@\n".as_bytes());
+        assert_eq!(super::Room::from(test_input).mean_roll_neighbors(), 1.0);
+        let test_input = std::io::BufReader::new("...\n".as_bytes());
+        assert_eq!(super::Room::from(test_input).mean_roll_neighbors(), 0.0);
+    }
+
+    #[test]
+    fn test_densest_region() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let room = super::Room::from(test_input);
+        // Rows 3 to 5 all have rolls in columns 3 to 5, the only full window.
+        assert_eq!(room.densest_region(), Some((3, 3, 9)));
+
+        let test_input = std::io::BufReader::new("...@\n.@@@\n.@@.\n".as_bytes());
+        assert_eq!(
+            super::Room::from(test_input).densest_region(),
+            Some((0, 1, 6))
+        );
+        let test_input = std::io::BufReader::new("....\n....\n....\n".as_bytes());
+        assert_eq!(
+            super::Room::from(test_input).densest_region(),
+            Some((0, 0, 0))
+        );
+        let test_input = std::io::BufReader::new("@@@@\n@@@@\n".as_bytes());
+        assert_eq!(super::Room::from(test_input).densest_region(), None);
+    }
+
+    #[test]
+    fn test_count_components() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        assert_eq!(super::Room::from(test_input).count_components(), 1);
+
+        let test_input = std::io::BufReader::new("@@..\n@@..\n...@\n".as_bytes());
+        assert_eq!(super::Room::from(test_input).count_components(), 2);
+        // Diagonal neighbors are connected.
+        let test_input = std::io::BufReader::new("@.\n.@\n".as_bytes());
+        assert_eq!(super::Room::from(test_input).count_components(), 1);
+        let test_input = std::io::BufReader::new("...\n".as_bytes());
+        assert_eq!(super::Room::from(test_input).count_components(), 0);
+    }
+
+    #[test]
+    fn test_has_movable() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let mut room = super::Room::from(test_input);
+        assert!(room.has_movable());
+        assert!(room.has_movable()); // querying does not change state
+        while room.sweep() > 0 {}
+        assert!(!room.has_movable());
+    }
+
+    #[test]
+    fn test_will_move() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let room = super::Room::from(test_input);
+        assert!(room.will_move(0, 2)); // initially movable
+        assert!(room.will_move(1, 1)); // movable after its neighbors are removed
+        assert!(!room.will_move(4, 4)); // stuck
+        assert!(!room.will_move(0, 0)); // not a roll
+        assert!(!room.will_move(20, 20)); // outside the room
+        assert_eq!(room.roll_positions().count(), 71); // unchanged by the queries
+    }
+
+    #[test]
+    fn test_state_fingerprint() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let mut room = super::Room::from(test_input);
+        let initial = room.state_fingerprint();
+        while room.sweep() > 0 {}
+        let stable = room.state_fingerprint();
+        assert_ne!(initial, stable);
+
+        // The surviving rolls in a larger room, plus an isolated roll which is removed.
+        let mut rolls: Vec<(usize, usize)> = room.roll_positions().collect();
+        rolls.push((11, 11));
+        let mut other = super::Room::from_coords(12, 12, &rolls).unwrap();
+        assert_ne!(other.state_fingerprint(), stable);
+        while other.sweep() > 0 {}
+        assert_eq!(other.state_fingerprint(), stable);
+
+        // Rooms which stabilize to nothing share the fingerprint of an empty room.
+        let test_input = std::io::BufReader::new("@@@\n@@@\n.@.\n".as_bytes());
+        let mut emptied = super::Room::from(test_input);
+        while emptied.sweep() > 0 {}
+        let empty = super::Room::from_coords(2, 2, &[]).unwrap();
+        assert_eq!(emptied.state_fingerprint(), empty.state_fingerprint());
+        assert_ne!(empty.state_fingerprint(), stable);
+    }
+
+    #[test]
+    fn test_render_movability() {
+        let test_input = std::io::BufReader::new("@@@\n@@@\n.@.\n".as_bytes());
+        let room = super::Room::from(test_input);
+        assert_eq!(room.render_movability(), "OoO\nooo\n.O.\n");
+    }
+
+    #[test]
+    fn test_from_coords() {
+        let rolls = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2), (2, 1)];
+        let room = super::Room::from_coords(3, 3, &rolls).unwrap();
+        assert_eq!(room.render_movability(), "OoO\nooo\n.O.\n");
+
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let rolls: Vec<(usize, usize)> = super::Room::from(test_input).roll_positions().collect();
+        let room = super::Room::from_coords(10, 10, &rolls).unwrap();
+        let movable = room.cells.entries().filter(|e| e.is_movable()).count();
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        assert_eq!(movable, super::count_initially_movable(test_input));
+
+        // Dimensions need not be square, and a room may have no rolls at all.
+        let room = super::Room::from_coords(4, 2, &[(1, 3)]).unwrap();
+        assert_eq!(room.render_movability(), "....\n...O\n");
+        let room = super::Room::from_coords(2, 2, &[]).unwrap();
+        assert!(!room.has_movable());
+    }
+
+    const SPARSE_INPUT: &str = "
+3 3
+0 0
+0 1
+0 2
+1 0
+1 1
+1 2
+2 1
+";
+
+    #[test]
+    fn test_from_sparse() {
+        let test_input = std::io::BufReader::new(SPARSE_INPUT.as_bytes());
+        let room = super::Room::from_sparse(test_input).unwrap();
+        assert_eq!(room.render_movability(), "OoO\nooo\n.O.\n");
+        let test_input = std::io::BufReader::new("@@@\n@@@\n.@.\n".as_bytes());
+        assert_eq!(
+            room.movable_count(),
+            super::count_initially_movable(test_input)
+        );
+
+        // Dimensions are `width height`, while rolls are `row col`.
+        let test_input = std::io::BufReader::new("5 2\n1 4\n".as_bytes());
+        let room = super::Room::from_sparse(test_input).unwrap();
+        assert_eq!((room.width, room.height), (5, 2));
+        assert_eq!(room.roll_positions().collect::<Vec<_>>(), vec![(1, 4)]);
+
+        assert_eq!(super::parse_pair("3 3"), Some((3, 3)));
+        assert_eq!(super::parse_pair("..@"), None);
+        assert_eq!(super::parse_pair("3 3 3"), None);
+        assert_eq!(super::parse_pair("3"), None);
+    }
+
+    #[test]
+    fn test_from_sparse_errors() {
+        use super::{ParseSparseError, Room};
+        let test_input = std::io::BufReader::new("3 3\n0 0\n1 x\n2 2\n".as_bytes());
+        assert_eq!(
+            Room::from_sparse(test_input).err(),
+            Some(ParseSparseError::ParseRoll("1 x".to_string()))
+        );
+        let test_input = std::io::BufReader::new("3\n0 0\n".as_bytes());
+        assert_eq!(
+            Room::from_sparse(test_input).err(),
+            Some(ParseSparseError::ParseDimensions)
+        );
+        let test_input = std::io::BufReader::new("5 2\n1 4\n2 0\n".as_bytes());
+        assert_eq!(
+            Room::from_sparse(test_input).err(),
+            Some(ParseSparseError::OutOfBounds(2, 0))
+        );
+        assert_eq!(
+            Room::from_coords(5, 2, &[(1, 5)]).err(),
+            Some(ParseSparseError::OutOfBounds(1, 5))
+        );
+    }
+
+    #[test]
+    fn test_weighted_rolls() {
+        // A weight of 1 behaves exactly like `@`.
+        let weighted = EXAMPLE_INPUT.replace('@', "1");
+        let test_input = std::io::BufReader::new(weighted.as_bytes());
+        assert_eq!(super::count_initially_movable(test_input), 13);
+        let test_input = std::io::BufReader::new(weighted.as_bytes());
+        assert_eq!(super::sweep_profile(test_input), vec![30, 9, 4]);
+
+        // The heavy roll keeps every roll touching it stuck, unlike the bottom roll.
+        let test_input = std::io::BufReader::new("191\n111\n.1.\n".as_bytes());
+        assert_eq!(super::count_initially_movable(test_input), 1);
+        let test_input = std::io::BufReader::new("191\n111\n.1.\n".as_bytes());
+        let room = super::Room::from(test_input);
+        assert_eq!(room.render_movability(), "ooo\nooo\n.O.\n");
+        assert_eq!(room.cells[(0, 0)].neighbors, 11);
+
+        // Removing a heavy roll frees its neighbors by its full weight.
+        let test_input = std::io::BufReader::new("14\n".as_bytes());
+        assert_eq!(super::removal_order(test_input), vec![(0, 1, 0), (0, 0, 1)]);
+        let test_input = std::io::BufReader::new("@@\n".as_bytes());
+        assert_eq!(super::removal_order(test_input), vec![(0, 0, 0), (0, 1, 0)]);
+    }
+
+    #[test]
+    fn test_cascade_cells() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let cascade = super::cascade_cells(test_input);
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let initially = super::count_initially_movable(test_input);
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let eventually = super::count_eventually_movable(test_input);
+        assert_eq!(cascade.len() + initially, eventually);
+        assert_eq!(cascade.len(), 30);
+
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let room = super::Room::from(test_input);
+        assert!(
+            cascade
+                .iter()
+                .all(|&(i, j)| !room.cells[(i, j)].is_movable())
+        );
+        assert!(cascade.contains(&(1, 1)));
+        assert!(!cascade.contains(&(0, 2))); // initially movable
+        assert!(!cascade.contains(&(4, 4))); // never removed
+
+        let test_input = std::io::BufReader::new("@@@\n@@@\n.@.\n".as_bytes());
+        assert_eq!(
+            super::cascade_cells(test_input),
+            vec![(1, 0), (1, 1), (1, 2), (0, 1)]
+        );
+    }
+
+    #[test]
+    fn test_removal_order() {
+        let test_input = std::io::BufReader::new("@@@\n@@@\n.@.\n".as_bytes());
+        let result = super::removal_order(test_input);
+        assert_eq!(
+            result,
+            vec![
+                (0, 0, 0),
+                (0, 2, 0),
+                (1, 0, 0),
+                (1, 1, 0),
+                (1, 2, 0),
+                (2, 1, 0),
+                (0, 1, 1)
+            ]
+        );
+
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let result = super::removal_order(test_input);
+        assert_eq!(result.len(), 43);
+        assert_eq!(&result[..3], &[(0, 2, 0), (0, 3, 0), (0, 5, 0)]);
+    }
+
     #[test]
     fn test_find_neighbors() {
         let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
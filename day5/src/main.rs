@@ -5,7 +5,11 @@ use std::str::FromStr;
 #[derive(Debug)]
 enum ParseRangeError {
     ParseRange,
+    #[allow(dead_code)] // only read through Debug
     ParseInt(ParseIntError),
+    Inverted,
+    ParseJson,
+    ParseOp,
 }
 
 #[derive(Debug, PartialEq)]
@@ -14,15 +18,28 @@ struct MyRange {
     end: usize,
 }
 
+/// Parses one bound of a range, ignoring surrounding whitespace and any `_` separators, such as in
+/// `13_873_831`.
+fn parse_bound(s: &str) -> Result<usize, ParseRangeError> {
+    s.trim()
+        .replace('_', "")
+        .parse()
+        .map_err(ParseRangeError::ParseInt)
+}
+
 impl FromStr for MyRange {
     type Err = ParseRangeError;
 
+    /// Accepts both the compact `3-5` form and a spaced form such as `13_873_831 - 16_714_933`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let Some((left, right)) = s.split_once('-') else {
             return Err(ParseRangeError::ParseRange);
         };
-        let start: usize = left.parse().map_err(ParseRangeError::ParseInt)?;
-        let end: usize = right.parse().map_err(ParseRangeError::ParseInt)?;
+        let start = parse_bound(left)?;
+        let end = parse_bound(right)?;
+        if start > end {
+            return Err(ParseRangeError::Inverted);
+        }
         Ok(MyRange { start, end })
     }
 }
@@ -42,67 +59,130 @@ impl PartialOrd for MyRange {
 }
 
 impl MyRange {
+    fn contains(&self, n: usize) -> bool {
+        self.start <= n && n <= self.end
+    }
+
     fn overlaps(&self, other: &MyRange) -> bool {
         !(self.end < other.start || other.end < self.start)
     }
 
-    /// Merge existing range into the receiver. The caller must ensure that the two ranges overlap.
+    /// Returns true if the ranges overlap or are separated by a gap of at most `gap` values, so
+    /// that `10-14` and `18-20` touch within a gap of 3.
+    fn touches_within(&self, other: &MyRange, gap: usize) -> bool {
+        let reach = gap.saturating_add(1);
+        !(self.end.saturating_add(reach) < other.start
+            || other.end.saturating_add(reach) < self.start)
+    }
+
+    /// Merge existing range into the receiver. The caller must ensure that the two ranges touch.
     fn merge(&mut self, other: &MyRange) {
         self.start = self.start.min(other.start);
         self.end = self.end.max(other.end);
     }
 
+    /// Returns the number of values in the range, or 0 if the range is inverted.
     fn total(&self) -> usize {
-        self.end - self.start + 1
+        self.end
+            .checked_sub(self.start)
+            .map_or(0, |span| span.saturating_add(1))
     }
 }
 
 /// A sorted vector of [MyRange]s, where no ranges may overlap. When adding a new range, if it
-/// overlaps with any existing range, those ranges should be merged.
+/// overlaps with or is adjacent to any existing range, those ranges should be merged.
 #[derive(Debug, PartialEq)]
-struct Ranges(Vec<MyRange>);
+struct Ranges {
+    ranges: Vec<MyRange>,
+    /// Ranges separated by at most this many missing values are also merged, so with the default
+    /// tolerance of 0 only adjacent ranges are.
+    gap_tolerance: usize,
+}
 
 impl Ranges {
-    fn from(lines: impl Iterator<Item = String>) -> Self {
-        lines
-            .skip_while(|line| line.is_empty())
-            .take_while(|line| !line.is_empty())
-            .map(|line| MyRange::from_str(&line).unwrap())
-            .fold(Ranges(Vec::new()), |mut acc, range| {
-                acc.add_range(range);
-                acc
-            })
+    /// Wraps ranges which are already sorted and disjoint, with the default gap tolerance of 0.
+    fn new(ranges: Vec<MyRange>) -> Self {
+        Ranges {
+            ranges,
+            gap_tolerance: 0,
+        }
     }
 
-    fn add_range(&mut self, mut new: MyRange) {
-        if self.0.is_empty() {
-            self.0.push(new);
-            return;
+    fn with_capacity(n: usize) -> Self {
+        Ranges::new(Vec::with_capacity(n))
+    }
+
+    /// Sets the gap tolerance, re-merging any existing ranges which now fall within it.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn with_gap_tolerance(mut self, gap: usize) -> Self {
+        self.gap_tolerance = gap;
+        for range in std::mem::take(&mut self.ranges) {
+            self.add_range(range);
         }
+        self
+    }
 
-        let Some((first_matching_index, first_matching_range)) = self
-            .0
-            .iter()
-            .enumerate()
-            .find(|&(_, range)| !(*range < new))
-        else {
+    /// Reserves space for as many ranges as the iterator's size hint guarantees, since every line
+    /// may turn out to be a disjoint range. Input which is already sorted and disjoint is appended
+    /// without searching the set.
+    fn from(mut lines: impl Iterator<Item = String>) -> Self {
+        parse_ranges_section(&mut lines).unwrap()
+    }
+
+    /// Pushes a range which lies entirely beyond the reach of the last range in the set, and
+    /// otherwise falls back to [Ranges::add_range].
+    fn append_or_add_range(&mut self, new: MyRange) {
+        let reach = self.gap_tolerance.saturating_add(1);
+        if self
+            .ranges
+            .last()
+            .is_none_or(|last| last.end.saturating_add(reach) < new.start)
+        {
+            self.ranges.push(new);
+        } else {
+            self.add_range(new);
+        }
+    }
+
+    /// Builds a set from `(start, end)` pairs without any string parsing, returning
+    /// [ParseRangeError::Inverted] if any pair has its start after its end.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn from_pairs(pairs: impl Iterator<Item = (usize, usize)>) -> Result<Self, ParseRangeError> {
+        let mut ranges = Ranges::with_capacity(pairs.size_hint().0);
+        for (start, end) in pairs {
+            if start > end {
+                return Err(ParseRangeError::Inverted);
+            }
+            ranges.add_range(MyRange { start, end });
+        }
+        Ok(ranges)
+    }
+
+    fn add_range(&mut self, mut new: MyRange) {
+        let gap = self.gap_tolerance;
+        let first_matching_index = self.first_matching_index(&new);
+        let Some(first_matching_range) = self.ranges.get(first_matching_index) else {
             // new range greater than any existing, so push it to the end
-            self.0.push(new);
+            self.ranges.push(new);
             return;
         };
         match first_matching_range.partial_cmp(&new) {
             Some(Ordering::Equal) => return, // they're identical
-            Some(Ordering::Greater) => return self.0.insert(first_matching_index, new),
-            _ => new.merge(first_matching_range), // they overlap, so find the first that doesn't
+            Some(Ordering::Greater) if !first_matching_range.touches_within(&new, gap) => {
+                return self.ranges.insert(first_matching_index, new);
+            }
+            // they touch, so find the first that doesn't, since merging may bridge a chain of
+            // ranges which are each within the gap tolerance of the growing range
+            _ => new.merge(first_matching_range),
         }
         let Some((first_non_matching_index, _)) = self
-            .0
+            .ranges
             .get((first_matching_index + 1)..)
             .unwrap()
             .iter()
             .enumerate()
             .find(|(_, range)| {
-                if range.overlaps(&new) {
+                if range.touches_within(&new, gap) {
                     new.merge(range);
                     return false;
                 }
@@ -110,45 +190,427 @@ impl Ranges {
             })
         else {
             // all remaining ranges overlap
-            let _ = self.0.drain(first_matching_index..);
-            self.0.push(new);
+            let _ = self.ranges.drain(first_matching_index..);
+            self.ranges.push(new);
             return;
         };
         let first_non_matching_index = first_non_matching_index + first_matching_index + 1; // adjust for skipped ranges
 
         // overwrite the first overlapping entry to preserve it in the vec
-        self.0[first_matching_index].merge(&new);
+        self.ranges[first_matching_index].merge(&new);
         // remove all other overlapping entries
         let _ = self
-            .0
+            .ranges
             .drain((first_matching_index + 1)..first_non_matching_index);
     }
 
+    /// Returns the index of the first range which is not less than the given range or which
+    /// touches it within the gap tolerance, or the number of ranges if there is none.
+    ///
+    /// A range which is less than the new range can only touch it by ending within the gap
+    /// tolerance of its start, so a range is skipped exactly when it ends more than the tolerance
+    /// before the new range starts. Since the ranges are sorted and disjoint, the skipped ranges
+    /// form a prefix, and the index can be found by binary search.
+    fn first_matching_index(&self, new: &MyRange) -> usize {
+        let reach = self.gap_tolerance.saturating_add(1);
+        self.ranges
+            .partition_point(|range| range.end.saturating_add(reach) < new.start)
+    }
+
+    /// Add a single number to the set, merging it with any range it touches.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn insert(&mut self, n: usize) {
+        self.add_range(MyRange { start: n, end: n });
+    }
+
+    /// Remove every number in the given range from the set, splitting any range which extends
+    /// beyond it on both sides.
+    fn remove_range(&mut self, removed: &MyRange) {
+        let mut kept = Vec::with_capacity(self.ranges.len() + 1);
+        for range in self.ranges.drain(..) {
+            if !range.overlaps(removed) {
+                kept.push(range);
+                continue;
+            }
+            if range.start < removed.start {
+                kept.push(MyRange {
+                    start: range.start,
+                    end: removed.start - 1,
+                });
+            }
+            if range.end > removed.end {
+                kept.push(MyRange {
+                    start: removed.end + 1,
+                    end: range.end,
+                });
+            }
+        }
+        self.ranges = kept;
+    }
+
     fn contains(&self, number: usize) -> bool {
-        match self.0.iter().find(|myrng| !(myrng.end < number)) {
-            Some(matching) => matching.start <= number,
+        let index = self.ranges.partition_point(|myrng| myrng.end < number);
+        match self.ranges.get(index) {
+            Some(matching) => matching.contains(number),
             None => false,
         }
     }
 
+    /// Tests the membership of every query at once, returning the results in the order of the
+    /// queries. The queries are visited in sorted order, so that the ranges are swept in a single
+    /// pass rather than searched once per query.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn contains_many(&self, queries: &[usize]) -> Vec<bool> {
+        let mut order: Vec<usize> = (0..queries.len()).collect();
+        order.sort_unstable_by_key(|&i| queries[i]);
+        let mut results = vec![false; queries.len()];
+        let mut ranges = self.ranges.iter().peekable();
+        for i in order {
+            let number = queries[i];
+            while ranges.next_if(|myrng| myrng.end < number).is_some() {}
+            results[i] = ranges.peek().is_some_and(|myrng| myrng.contains(number));
+        }
+        results
+    }
+
+    /// Lazily yields every contained number within `[start, end]` in ascending order, clipping the
+    /// ranges at either end of the window.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn iter_values_in(&self, start: usize, end: usize) -> impl Iterator<Item = usize> + '_ {
+        let index = self.ranges.partition_point(|myrng| myrng.end < start);
+        self.ranges[index..]
+            .iter()
+            .take_while(move |myrng| myrng.start <= end)
+            .flat_map(move |myrng| myrng.start.max(start)..=myrng.end.min(end))
+    }
+
+    /// Lazily yields the uncovered values between each pair of adjacent ranges in the set, in
+    /// ascending order. Values before the first range and after the last range are not gaps.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn gaps(&self) -> impl Iterator<Item = MyRange> + '_ {
+        self.ranges.windows(2).map(|pair| MyRange {
+            start: pair[0].end + 1,
+            end: pair[1].start - 1,
+        })
+    }
+
+    /// Returns the widest of the [Ranges::gaps], breaking ties toward the earliest, or `None` if
+    /// the set has fewer than two ranges.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn max_gap(&self) -> Option<MyRange> {
+        self.gaps().reduce(|widest, gap| {
+            if gap.total() > widest.total() {
+                gap
+            } else {
+                widest
+            }
+        })
+    }
+
+    /// Returns true if the given range overlaps any range in the set. Adjacent ranges do not
+    /// overlap, even though [Ranges::add_range] would merge them.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn overlaps_any(&self, r: &MyRange) -> bool {
+        let index = self.ranges.partition_point(|myrng| myrng.end < r.start);
+        self.ranges
+            .get(index)
+            .is_some_and(|myrng| myrng.overlaps(r))
+    }
+
+    /// Builds a bitmap with one bit per value in `[0, max]`, set if the value is contained in the
+    /// set. Returns [None] if `max` exceeds [MAX_BITMAP_UNIVERSE], to bound memory use.
+    fn build_bitmap(&self, max: usize) -> Option<Vec<u64>> {
+        if max > MAX_BITMAP_UNIVERSE {
+            return None;
+        }
+        let mut bits = vec![0_u64; max / 64 + 1];
+        for range in self.ranges.iter().take_while(|range| range.start <= max) {
+            for n in range.start..=range.end.min(max) {
+                bits[n / 64] |= 1 << (n % 64);
+            }
+        }
+        Some(bits)
+    }
+
+    /// Returns a [ContainsIndex] which uses a bitmap for values up to `max` when the universe is
+    /// small enough, and falls back to searching the ranges otherwise.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn contains_index(&self, max: usize) -> ContainsIndex<'_> {
+        ContainsIndex {
+            ranges: self,
+            bitmap: self.build_bitmap(max),
+            max,
+        }
+    }
+
     fn total(&self) -> usize {
-        self.0.iter().map(|r| r.total()).sum()
+        self.ranges.iter().map(|r| r.total()).sum()
+    }
+
+    /// Returns the fraction of the values in `[0, max]` which are contained in the set, clipping any
+    /// range which extends beyond `max`.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn coverage_ratio(&self, max: usize) -> f64 {
+        let covered: usize = self
+            .ranges
+            .iter()
+            .take_while(|range| range.start <= max)
+            .map(|range| {
+                MyRange {
+                    start: range.start,
+                    end: range.end.min(max),
+                }
+                .total()
+            })
+            .sum();
+        // computed in floating point, since max + 1 overflows when max is usize::MAX
+        covered as f64 / (max as f64 + 1.0)
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn num_ranges(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Keep only the ranges which satisfy the predicate. Since ranges are only removed, the set
+    /// remains sorted and non-overlapping.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn retain(&mut self, f: impl Fn(&MyRange) -> bool) {
+        self.ranges.retain(f);
+    }
+
+    /// Translates every range by `delta`. Values which would fall below zero or above
+    /// [usize::MAX] are dropped, so a range crossing either bound is clamped to it and a range
+    /// entirely beyond it is removed. Since every remaining start (when shifting up) or end (when
+    /// shifting down) moves by exactly `delta`, the set remains sorted and disjoint.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn shift(&mut self, delta: isize) {
+        let magnitude = delta.unsigned_abs();
+        self.ranges.retain_mut(|range| {
+            if delta >= 0 {
+                let Some(start) = range.start.checked_add(magnitude) else {
+                    return false;
+                };
+                range.start = start;
+                range.end = range.end.saturating_add(magnitude);
+            } else {
+                let Some(end) = range.end.checked_sub(magnitude) else {
+                    return false;
+                };
+                range.start = range.start.saturating_sub(magnitude);
+                range.end = end;
+            }
+            true
+        });
+        debug_assert!(
+            self.ranges
+                .windows(2)
+                .all(|pair| pair[0].end.saturating_add(1) < pair[1].start),
+            "shifted ranges must remain sorted and disjoint"
+        );
+    }
+
+    /// Restricts the set to the values within `[lo, hi]`, trimming any range which crosses either
+    /// bound and dropping any range entirely outside them. Empties the set if `lo > hi`.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn clamp(&mut self, lo: usize, hi: usize) {
+        self.ranges.retain_mut(|range| {
+            range.start = range.start.max(lo);
+            range.end = range.end.min(hi);
+            range.start <= range.end
+        });
+    }
+
+    /// Split the set into the ranges at or below the pivot and the ranges above it. A range which
+    /// contains the pivot is split in two, with the pivot landing in the left half.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn split_at(mut self, pivot: usize) -> (Ranges, Ranges) {
+        let index = self
+            .ranges
+            .iter()
+            .position(|range| range.end > pivot)
+            .unwrap_or(self.ranges.len());
+        let mut right = self.ranges.split_off(index);
+        if let Some(first) = right.first_mut()
+            && first.start <= pivot
+        {
+            self.ranges.push(MyRange {
+                start: first.start,
+                end: pivot,
+            });
+            first.start = pivot + 1; // cannot overflow, since first.end > pivot
+        }
+        let gap = self.gap_tolerance;
+        (self, Ranges::new(right).with_gap_tolerance(gap))
+    }
+
+    /// Serializes the ranges as a JSON array of `[start, end]` pairs, such as `[[3,5],[10,20]]`.
+    /// The gap tolerance is not included.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn to_json(&self) -> String {
+        let pairs: Vec<String> = self
+            .ranges
+            .iter()
+            .map(|range| format!("[{},{}]", range.start, range.end))
+            .collect();
+        format!("[{}]", pairs.join(","))
+    }
+
+    /// Parses a JSON array of `[start, end]` pairs as written by [Ranges::to_json], allowing
+    /// whitespace between tokens. The pairs need not be sorted or disjoint, since they are merged
+    /// as they are added, and the resulting set has the default gap tolerance of 0. Returns
+    /// [ParseRangeError::ParseJson] if the array is malformed, including any number with a sign.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn from_json(s: &str) -> Result<Self, ParseRangeError> {
+        let mut rest = s.trim_start();
+        json_token(&mut rest, '[')?;
+        let mut pairs = Vec::new();
+        if json_token(&mut rest, ']').is_err() {
+            loop {
+                json_token(&mut rest, '[')?;
+                let start = json_number(&mut rest)?;
+                json_token(&mut rest, ',')?;
+                let end = json_number(&mut rest)?;
+                json_token(&mut rest, ']')?;
+                pairs.push((start, end));
+                if json_token(&mut rest, ']').is_ok() {
+                    break;
+                }
+                json_token(&mut rest, ',')?;
+            }
+        }
+        if !rest.is_empty() {
+            return Err(ParseRangeError::ParseJson);
+        }
+        Ranges::from_pairs(pairs.into_iter())
+    }
+}
+
+/// Consumes the expected character from the front of the input, along with any whitespace after it.
+fn json_token(s: &mut &str, token: char) -> Result<(), ParseRangeError> {
+    let rest = s.strip_prefix(token).ok_or(ParseRangeError::ParseJson)?;
+    *s = rest.trim_start();
+    Ok(())
+}
+
+/// Consumes the unsigned integer at the front of the input, along with any whitespace after it.
+fn json_number(s: &mut &str) -> Result<usize, ParseRangeError> {
+    let len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if len == 0 {
+        return Err(ParseRangeError::ParseJson);
+    }
+    let num = s[..len].parse().map_err(ParseRangeError::ParseInt)?;
+    *s = s[len..].trim_start();
+    Ok(num)
+}
+
+impl FromIterator<MyRange> for Ranges {
+    fn from_iter<I: IntoIterator<Item = MyRange>>(iter: I) -> Self {
+        let mut ranges = Ranges::new(Vec::new());
+        ranges.extend(iter);
+        ranges
+    }
+}
+
+/// Adds each range in turn, merging according to the set's gap tolerance.
+impl Extend<MyRange> for Ranges {
+    fn extend<I: IntoIterator<Item = MyRange>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.ranges.reserve(iter.size_hint().0);
+        for range in iter {
+            self.add_range(range);
+        }
+    }
+}
+
+/// The largest universe for which [Ranges::build_bitmap] will build a bitmap, which bounds the
+/// bitmap to 8 MiB.
+const MAX_BITMAP_UNIVERSE: usize = 1 << 26;
+
+/// Answers membership queries against a [Ranges] in O(1) for values covered by its bitmap.
+struct ContainsIndex<'a> {
+    ranges: &'a Ranges,
+    bitmap: Option<Vec<u64>>,
+    max: usize,
+}
+
+impl ContainsIndex<'_> {
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn contains(&self, number: usize) -> bool {
+        match &self.bitmap {
+            Some(bits) if number <= self.max => bits[number / 64] & (1 << (number % 64)) != 0,
+            _ => self.ranges.contains(number),
+        }
+    }
+}
+
+/// Parses the ranges section, skipping any leading blank lines and stopping after the blank line
+/// which separates it from the numbers section, so that the iterator is left positioned at the
+/// first number. Reserves space for as many ranges as the iterator's size hint guarantees.
+fn parse_ranges_section(
+    lines: &mut impl Iterator<Item = String>,
+) -> Result<Ranges, ParseRangeError> {
+    let mut ranges = Ranges::with_capacity(lines.size_hint().0);
+    for line in lines.skip_while(|line| line.is_empty()) {
+        if line.is_empty() {
+            break;
+        }
+        ranges.append_or_add_range(MyRange::from_str(&line)?);
     }
+    Ok(ranges)
 }
 
 fn count_fresh(r: impl std::io::BufRead) -> (usize, usize) {
+    let (available, all) = count_fresh_detailed(r);
+    (available.len(), all)
+}
+
+/// Returns the requested numbers which are fresh, in input order, along with the total number of
+/// fresh ingredients.
+fn count_fresh_detailed(r: impl std::io::BufRead) -> (Vec<usize>, usize) {
     let mut lines = r.lines().map_while(Result::ok);
     let ranges = Ranges::from(&mut lines);
     let available = lines
         .take_while(|line| !line.is_empty())
         .map(|line| line.parse::<usize>().unwrap())
         .filter(|num| ranges.contains(*num))
-        .count();
+        .collect();
     let all = ranges.total();
     (available, all)
 }
 
+/// Applies a sequence of operations, one per line, where `+10-20` adds the range `10-20` to the set
+/// and `-15-18` removes the range `15-18` from it. Returns [ParseRangeError::ParseOp] if a line
+/// begins with neither, or the error of the first range which cannot be parsed.
+fn apply_ops(r: impl std::io::BufRead) -> Result<Ranges, ParseRangeError> {
+    let mut ranges = Ranges::new(Vec::new());
+    for line in r.lines().map_while(Result::ok).filter(|l| !l.is_empty()) {
+        if let Some(range) = line.strip_prefix('+') {
+            ranges.add_range(MyRange::from_str(range)?);
+        } else if let Some(range) = line.strip_prefix('-') {
+            ranges.remove_range(&MyRange::from_str(range)?);
+        } else {
+            return Err(ParseRangeError::ParseOp);
+        }
+    }
+    Ok(ranges)
+}
+
 fn main() {
+    if std::env::args().skip(1).any(|arg| arg == "--ops") {
+        let ranges = match apply_ops(std::io::stdin().lock()) {
+            Ok(ranges) => ranges,
+            Err(e) => {
+                eprintln!("Error applying operations: {e:?}");
+                return;
+            }
+        };
+        for range in &ranges.ranges {
+            println!("{}-{}", range.start, range.end);
+        }
+        println!("all fresh ingredients: {}", ranges.total());
+        return;
+    }
     let (available, all) = count_fresh(std::io::stdin().lock());
     println!("available fresh ingredients: {available}");
     println!("all fresh ingredients: {all}");
@@ -156,7 +618,11 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use crate::{count_fresh, MyRange, Ranges};
+    use crate::{
+        MyRange, ParseRangeError, Ranges, apply_ops, count_fresh, count_fresh_detailed,
+        parse_ranges_section,
+    };
+    use std::str::FromStr;
 
     const EXAMPLE_INPUT: &str = "
 3-5
@@ -178,6 +644,755 @@ mod tests {
         assert_eq!((available, all), (3, 14));
     }
 
+    #[test]
+    fn test_parse_inverted_range() {
+        assert!(matches!(
+            MyRange::from_str("20-10"),
+            Err(ParseRangeError::Inverted)
+        ));
+        assert_eq!(
+            MyRange::from_str("10-10").unwrap(),
+            MyRange { start: 10, end: 10 }
+        );
+        assert_eq!(MyRange { start: 20, end: 10 }.total(), 0);
+        assert_eq!(MyRange { start: 10, end: 20 }.total(), 11);
+    }
+
+    #[test]
+    fn test_my_range_contains() {
+        let range = MyRange { start: 10, end: 14 };
+        assert!(!range.contains(9));
+        assert!(range.contains(10));
+        assert!(range.contains(12));
+        assert!(range.contains(14));
+        assert!(!range.contains(15));
+        assert!(MyRange { start: 7, end: 7 }.contains(7));
+        assert!(!MyRange { start: 20, end: 10 }.contains(15));
+    }
+
+    #[test]
+    fn test_parse_spaced_range() {
+        assert_eq!(
+            MyRange::from_str("13_873_831 - 16_714_933").unwrap(),
+            MyRange::from_str("13873831-16714933").unwrap()
+        );
+        assert_eq!(
+            MyRange::from_str(" 3 -5").unwrap(),
+            MyRange { start: 3, end: 5 }
+        );
+        assert!(matches!(
+            MyRange::from_str("3 5"),
+            Err(ParseRangeError::ParseRange)
+        ));
+        assert!(matches!(
+            MyRange::from_str("1 000-2"),
+            Err(ParseRangeError::ParseInt(_))
+        ));
+    }
+
+    fn example_ranges() -> Ranges {
+        Ranges::from(EXAMPLE_INPUT.lines().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_ranges_section() {
+        let mut lines = EXAMPLE_INPUT.lines().map(|s| s.to_string());
+        let ranges = parse_ranges_section(&mut lines).unwrap();
+        assert_eq!(ranges, example_ranges());
+        let rest: Vec<String> = lines.collect();
+        assert_eq!(rest, ["1", "5", "8", "11", "17", "32"]);
+
+        let mut lines = ["3-5", "oops", "", "1"].into_iter().map(|s| s.to_string());
+        assert!(matches!(
+            parse_ranges_section(&mut lines),
+            Err(ParseRangeError::ParseRange)
+        ));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let ranges = example_ranges();
+        let json = ranges.to_json();
+        assert_eq!(json, "[[3,5],[10,20]]");
+        assert_eq!(Ranges::from_json(&json).unwrap(), ranges);
+
+        let ranges = Ranges::from(RANGE_INPUT.lines().map(|s| s.to_string()));
+        assert_eq!(Ranges::from_json(&ranges.to_json()).unwrap(), ranges);
+
+        let empty = Ranges::new(Vec::new());
+        assert_eq!(empty.to_json(), "[]");
+        assert_eq!(Ranges::from_json(" [ ] ").unwrap(), empty);
+
+        // Unsorted and overlapping pairs are merged on load.
+        assert_eq!(
+            Ranges::from_json("[[16, 20], [3, 5],\n [10, 14], [12, 18]]").unwrap(),
+            example_ranges()
+        );
+        assert_eq!(
+            Ranges::from_json(" [ [ 3 , 5 ] , [10,20] ] \n").unwrap(),
+            example_ranges()
+        );
+        for malformed in [
+            "",
+            "[",
+            "[[3,5]",
+            "[3,5]",
+            "[[3,5],]",
+            "[[3]]",
+            "{}",
+            "[[3,5]] x",
+            "[[1 2,30]]",
+            "[[+3,5]]",
+            "[[3,+5]]",
+            "[[3,-5]]",
+        ] {
+            assert!(
+                Ranges::from_json(malformed).is_err(),
+                "{malformed} should not parse"
+            );
+        }
+        // Whitespace may only separate tokens, and signs are never accepted.
+        assert!(matches!(
+            Ranges::from_json("[[1 2,30]]"),
+            Err(ParseRangeError::ParseJson)
+        ));
+        assert!(matches!(
+            Ranges::from_json("[[+1,30]]"),
+            Err(ParseRangeError::ParseJson)
+        ));
+        assert!(matches!(
+            Ranges::from_json("[[1,99999999999999999999999]]"),
+            Err(ParseRangeError::ParseInt(_))
+        ));
+        assert!(matches!(
+            Ranges::from_json("[[5,3]]"),
+            Err(ParseRangeError::Inverted)
+        ));
+    }
+
+    #[test]
+    fn test_from_pairs() {
+        let pairs = [(3, 5), (10, 14), (16, 20), (12, 18)];
+        let ranges = Ranges::from_pairs(pairs.into_iter()).unwrap();
+        assert_eq!(ranges, example_ranges());
+
+        let pairs: Vec<(usize, usize)> = RANGE_INPUT
+            .lines()
+            .map(|line| {
+                let range = MyRange::from_str(line).unwrap();
+                (range.start, range.end)
+            })
+            .collect();
+        assert_eq!(
+            Ranges::from_pairs(pairs.into_iter()).unwrap(),
+            Ranges::from(RANGE_INPUT.lines().map(|s| s.to_string()))
+        );
+
+        assert_eq!(
+            Ranges::from_pairs(std::iter::empty()).unwrap().num_ranges(),
+            0
+        );
+        assert!(matches!(
+            Ranges::from_pairs([(3, 5), (20, 10)].into_iter()),
+            Err(ParseRangeError::Inverted)
+        ));
+    }
+
+    #[test]
+    fn test_collect_and_extend() {
+        let ranges: Ranges = [(3, 5), (10, 14), (16, 20), (12, 18)]
+            .into_iter()
+            .map(|(start, end)| MyRange { start, end })
+            .collect();
+        assert_eq!(ranges, example_ranges());
+
+        let collected: Ranges = RANGE_INPUT
+            .lines()
+            .map(|line| MyRange::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            collected,
+            Ranges::from(RANGE_INPUT.lines().map(|s| s.to_string()))
+        );
+
+        let mut ranges = example_ranges();
+        ranges.extend(vec![
+            MyRange { start: 6, end: 7 },
+            MyRange { start: 30, end: 40 },
+        ]);
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 3, end: 7 },
+                MyRange { start: 10, end: 20 },
+                MyRange { start: 30, end: 40 }
+            ])
+        );
+
+        let mut ranges = Ranges::new(Vec::new()).with_gap_tolerance(3);
+        ranges.extend([
+            MyRange { start: 10, end: 14 },
+            MyRange { start: 18, end: 20 },
+        ]);
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![MyRange { start: 10, end: 20 }]).with_gap_tolerance(3)
+        );
+    }
+
+    #[test]
+    fn test_split_at_inside_range() {
+        let (left, right) = example_ranges().split_at(15);
+        assert_eq!(
+            left,
+            Ranges::new(vec![
+                MyRange { start: 3, end: 5 },
+                MyRange { start: 10, end: 15 }
+            ])
+        );
+        assert_eq!(right, Ranges::new(vec![MyRange { start: 16, end: 20 }]));
+    }
+
+    #[test]
+    fn test_split_at_gap() {
+        let (left, right) = example_ranges().split_at(7);
+        assert_eq!(left, Ranges::new(vec![MyRange { start: 3, end: 5 }]));
+        assert_eq!(right, Ranges::new(vec![MyRange { start: 10, end: 20 }]));
+    }
+
+    #[test]
+    fn test_split_at_boundaries() {
+        let (left, right) = example_ranges().split_at(5);
+        assert_eq!(left, Ranges::new(vec![MyRange { start: 3, end: 5 }]));
+        assert_eq!(right, Ranges::new(vec![MyRange { start: 10, end: 20 }]));
+
+        let (left, right) = example_ranges().split_at(10);
+        assert_eq!(
+            left,
+            Ranges::new(vec![
+                MyRange { start: 3, end: 5 },
+                MyRange { start: 10, end: 10 }
+            ])
+        );
+        assert_eq!(right, Ranges::new(vec![MyRange { start: 11, end: 20 }]));
+
+        let (left, right) = example_ranges().split_at(20);
+        assert_eq!(left, example_ranges());
+        assert_eq!(right, Ranges::new(vec![]));
+
+        let (left, right) = example_ranges().split_at(0);
+        assert_eq!(left, Ranges::new(vec![]));
+        assert_eq!(right, example_ranges());
+    }
+
+    #[test]
+    fn test_iter_values_in() {
+        let ranges = example_ranges();
+        let values: Vec<usize> = ranges.iter_values_in(4, 12).collect();
+        assert_eq!(values, vec![4, 5, 10, 11, 12]);
+        let values: Vec<usize> = ranges.iter_values_in(0, 100).collect();
+        assert_eq!(values.len(), ranges.total());
+        assert_eq!(ranges.iter_values_in(6, 9).count(), 0);
+        assert_eq!(ranges.iter_values_in(21, 30).count(), 0);
+        assert_eq!(ranges.iter_values_in(12, 4).count(), 0);
+        assert_eq!(ranges.iter_values_in(20, 20).collect::<Vec<_>>(), vec![20]);
+
+        // Nothing is materialized, so a huge window costs only what is consumed.
+        let ranges = Ranges::new(vec![MyRange {
+            start: 0,
+            end: usize::MAX,
+        }]);
+        let values: Vec<usize> = ranges.iter_values_in(5, usize::MAX).take(3).collect();
+        assert_eq!(values, vec![5, 6, 7]);
+    }
+
+    #[test]
+    fn test_contains_many() {
+        let ranges = example_ranges();
+        let queries = [32, 1, 17, 5, 11, 8, 17, 0, 20, 21];
+        assert_eq!(
+            ranges.contains_many(&queries),
+            vec![
+                false, false, true, true, true, false, true, false, true, false
+            ]
+        );
+        let queries: Vec<usize> = (0..25).rev().collect();
+        let expected: Vec<bool> = queries.iter().map(|&n| ranges.contains(n)).collect();
+        assert_eq!(ranges.contains_many(&queries), expected);
+        assert_eq!(ranges.contains_many(&[]), Vec::<bool>::new());
+    }
+
+    #[test]
+    fn test_gaps() {
+        let ranges = example_ranges();
+        assert_eq!(
+            ranges.gaps().collect::<Vec<_>>(),
+            vec![MyRange { start: 6, end: 9 }]
+        );
+        assert_eq!(ranges.max_gap(), Some(MyRange { start: 6, end: 9 }));
+
+        let ranges = Ranges::from_pairs([(0, 1), (4, 5), (7, 7), (10, 20)].into_iter()).unwrap();
+        assert_eq!(
+            ranges.gaps().collect::<Vec<_>>(),
+            vec![
+                MyRange { start: 2, end: 3 },
+                MyRange { start: 6, end: 6 },
+                MyRange { start: 8, end: 9 },
+            ]
+        );
+        assert_eq!(ranges.max_gap(), Some(MyRange { start: 2, end: 3 }));
+
+        let ranges = Ranges::from_pairs([(3, 5)].into_iter()).unwrap();
+        assert_eq!(ranges.gaps().count(), 0);
+        assert_eq!(ranges.max_gap(), None);
+    }
+
+    #[test]
+    fn test_overlaps_any() {
+        let ranges = example_ranges();
+        assert!(!ranges.overlaps_any(&MyRange { start: 6, end: 9 }));
+        assert!(!ranges.overlaps_any(&MyRange { start: 0, end: 2 }));
+        assert!(!ranges.overlaps_any(&MyRange { start: 21, end: 30 }));
+        assert!(ranges.overlaps_any(&MyRange { start: 4, end: 12 }));
+        assert!(ranges.overlaps_any(&MyRange { start: 0, end: 30 }));
+        assert!(ranges.overlaps_any(&MyRange { start: 20, end: 20 }));
+        assert!(ranges.overlaps_any(&MyRange { start: 5, end: 9 }));
+        assert!(!Ranges::new(Vec::new()).overlaps_any(&MyRange { start: 0, end: 30 }));
+    }
+
+    #[test]
+    fn test_contains_index() {
+        let mut ranges = example_ranges();
+        ranges.add_range(MyRange {
+            start: 60,
+            end: 200,
+        });
+        for max in [0, 4, 15, 63, 64, 100, 1000] {
+            let index = ranges.contains_index(max);
+            assert!(index.bitmap.is_some());
+            for n in 0..=250 {
+                assert_eq!(index.contains(n), ranges.contains(n), "max {max}, n {n}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_contains_index_too_large() {
+        let ranges = example_ranges();
+        assert!(ranges.build_bitmap(usize::MAX).is_none());
+        let index = ranges.contains_index(usize::MAX);
+        assert!(index.bitmap.is_none());
+        for n in 0..=25 {
+            assert_eq!(index.contains(n), ranges.contains(n));
+        }
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let mut ranges = Ranges::with_capacity(100);
+        let capacity = ranges.ranges.capacity();
+        assert!(capacity >= 100);
+        for i in 0..100 {
+            ranges.add_range(MyRange {
+                start: i * 10,
+                end: i * 10 + 5,
+            });
+        }
+        assert_eq!(ranges.num_ranges(), 100);
+        assert_eq!(ranges.ranges.capacity(), capacity);
+
+        let lines: Vec<String> = RANGE_INPUT.lines().map(|s| s.to_string()).collect();
+        let ranges = Ranges::from(lines.into_iter());
+        assert_eq!(ranges.num_ranges(), 19);
+        assert!(ranges.ranges.capacity() >= 20);
+    }
+
+    #[test]
+    fn test_coverage_ratio() {
+        let ranges = example_ranges();
+        assert_eq!(ranges.coverage_ratio(20), 14.0 / 21.0);
+        assert_eq!(ranges.coverage_ratio(19), 13.0 / 20.0);
+        assert_eq!(ranges.coverage_ratio(15), 9.0 / 16.0);
+        assert_eq!(ranges.coverage_ratio(99), 14.0 / 100.0);
+        assert_eq!(ranges.coverage_ratio(2), 0.0);
+        assert_eq!(ranges.coverage_ratio(0), 0.0);
+        assert_eq!(Ranges::new(Vec::new()).coverage_ratio(10), 0.0);
+        assert_eq!(Ranges::new(Vec::new()).coverage_ratio(0), 0.0);
+
+        let ranges = Ranges::new(vec![MyRange { start: 0, end: 5 }]);
+        assert_eq!(ranges.coverage_ratio(0), 1.0);
+        assert_eq!(ranges.coverage_ratio(3), 1.0);
+        let ranges = Ranges::new(vec![MyRange {
+            start: 0,
+            end: usize::MAX,
+        }]);
+        assert_eq!(ranges.coverage_ratio(usize::MAX), 1.0);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut ranges = example_ranges();
+        ranges.retain(|r| r.total() >= 3);
+        assert_eq!(ranges, example_ranges());
+
+        ranges.insert(1);
+        ranges.add_range(MyRange { start: 7, end: 8 });
+        ranges.add_range(MyRange { start: 30, end: 40 });
+        ranges.retain(|r| r.total() >= 3);
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 3, end: 5 },
+                MyRange { start: 10, end: 20 },
+                MyRange { start: 30, end: 40 },
+            ])
+        );
+
+        ranges.retain(|r| r.total() >= 11);
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 10, end: 20 },
+                MyRange { start: 30, end: 40 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_clamp() {
+        let mut ranges =
+            Ranges::from_pairs([(3, 5), (10, 14), (16, 20), (30, 40)].into_iter()).unwrap();
+        ranges.clamp(4, 18);
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 4, end: 5 },
+                MyRange { start: 10, end: 14 },
+                MyRange { start: 16, end: 18 },
+            ])
+        );
+        ranges.clamp(6, 9);
+        assert_eq!(ranges, Ranges::new(vec![]));
+
+        let mut ranges = example_ranges();
+        ranges.clamp(0, usize::MAX);
+        assert_eq!(ranges, example_ranges());
+        ranges.clamp(5, 3);
+        assert_eq!(ranges.num_ranges(), 0);
+    }
+
+    #[test]
+    fn test_shift() {
+        let mut ranges = example_ranges();
+        ranges.shift(5);
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 8, end: 10 },
+                MyRange { start: 15, end: 25 }
+            ])
+        );
+        ranges.shift(-5);
+        assert_eq!(ranges, example_ranges());
+        ranges.shift(0);
+        assert_eq!(ranges, example_ranges());
+    }
+
+    #[test]
+    fn test_shift_clamps_at_zero() {
+        let mut ranges = example_ranges();
+        ranges.shift(-4);
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 0, end: 1 },
+                MyRange { start: 6, end: 16 }
+            ])
+        );
+        ranges.shift(-7);
+        assert_eq!(ranges, Ranges::new(vec![MyRange { start: 0, end: 9 }]));
+        ranges.shift(-10);
+        assert_eq!(ranges, Ranges::new(vec![]));
+    }
+
+    #[test]
+    fn test_shift_clamps_at_max() {
+        let mut ranges = Ranges::new(vec![
+            MyRange {
+                start: usize::MAX - 10,
+                end: usize::MAX - 8,
+            },
+            MyRange {
+                start: usize::MAX - 6,
+                end: usize::MAX - 1,
+            },
+        ]);
+        ranges.shift(4);
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange {
+                    start: usize::MAX - 6,
+                    end: usize::MAX - 4,
+                },
+                MyRange {
+                    start: usize::MAX - 2,
+                    end: usize::MAX,
+                },
+            ])
+        );
+        ranges.shift(3);
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![MyRange {
+                start: usize::MAX - 3,
+                end: usize::MAX - 1,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_remove_range() {
+        let mut ranges = example_ranges();
+        ranges.remove_range(&MyRange { start: 4, end: 12 });
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 3, end: 3 },
+                MyRange { start: 13, end: 20 }
+            ])
+        );
+        ranges.remove_range(&MyRange { start: 6, end: 9 });
+        ranges.remove_range(&MyRange { start: 20, end: 30 });
+        ranges.remove_range(&MyRange { start: 0, end: 3 });
+        assert_eq!(ranges, Ranges::new(vec![MyRange { start: 13, end: 19 }]));
+    }
+
+    #[test]
+    fn test_apply_ops() {
+        let input = std::io::BufReader::new("+10-20\n-15-18\n".as_bytes());
+        assert_eq!(
+            apply_ops(input).unwrap(),
+            Ranges::new(vec![
+                MyRange { start: 10, end: 14 },
+                MyRange { start: 19, end: 20 }
+            ])
+        );
+        let input = std::io::BufReader::new("+10-20\n-15-18\n+3-5\n+16-17\n-0-3".as_bytes());
+        assert_eq!(
+            apply_ops(input).unwrap(),
+            Ranges::new(vec![
+                MyRange { start: 4, end: 5 },
+                MyRange { start: 10, end: 14 },
+                MyRange { start: 16, end: 17 },
+                MyRange { start: 19, end: 20 }
+            ])
+        );
+
+        let input = std::io::BufReader::new("+10-20\n15-18\n".as_bytes());
+        assert!(matches!(apply_ops(input), Err(ParseRangeError::ParseOp)));
+        let input = std::io::BufReader::new("+10-20\n-18-15\n".as_bytes());
+        assert!(matches!(apply_ops(input), Err(ParseRangeError::Inverted)));
+        let input = std::io::BufReader::new("+10-x\n".as_bytes());
+        assert!(matches!(
+            apply_ops(input),
+            Err(ParseRangeError::ParseInt(_))
+        ));
+    }
+
+    #[test]
+    fn test_insert_adjacent() {
+        let mut ranges = Ranges::new(Vec::new());
+        for n in [10, 12, 11] {
+            ranges.insert(n);
+        }
+        assert_eq!(ranges, Ranges::new(vec![MyRange { start: 10, end: 12 }]));
+
+        let mut ranges = example_ranges();
+        ranges.insert(6);
+        ranges.insert(9);
+        ranges.insert(30);
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 3, end: 6 },
+                MyRange { start: 9, end: 20 },
+                MyRange { start: 30, end: 30 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_gap_tolerance() {
+        let mut ranges = Ranges::new(Vec::new()).with_gap_tolerance(3);
+        ranges.add_range(MyRange { start: 10, end: 14 });
+        ranges.add_range(MyRange { start: 18, end: 20 });
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![MyRange { start: 10, end: 20 }]).with_gap_tolerance(3)
+        );
+        ranges.add_range(MyRange { start: 25, end: 30 });
+        ranges.add_range(MyRange { start: 0, end: 5 });
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 0, end: 5 },
+                MyRange { start: 10, end: 20 },
+                MyRange { start: 25, end: 30 }
+            ])
+            .with_gap_tolerance(3)
+        );
+        // within the tolerance of the new range and then of each other in turn
+        ranges.add_range(MyRange { start: 7, end: 7 });
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 0, end: 20 },
+                MyRange { start: 25, end: 30 }
+            ])
+            .with_gap_tolerance(3)
+        );
+        ranges.add_range(MyRange { start: 23, end: 23 });
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![MyRange { start: 0, end: 30 }]).with_gap_tolerance(3)
+        );
+    }
+
+    #[test]
+    fn test_gap_tolerance_bridges_chain() {
+        let mut ranges = Ranges::new(Vec::new()).with_gap_tolerance(3);
+        for start in [0, 6, 12, 18] {
+            ranges.add_range(MyRange {
+                start,
+                end: start + 1,
+            });
+        }
+        assert_eq!(ranges.num_ranges(), 4);
+        ranges.add_range(MyRange { start: 4, end: 15 });
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![MyRange { start: 0, end: 19 }]).with_gap_tolerance(3)
+        );
+    }
+
+    #[test]
+    fn test_with_gap_tolerance_remerges() {
+        assert_eq!(
+            example_ranges().with_gap_tolerance(3),
+            Ranges {
+                gap_tolerance: 3,
+                ..example_ranges()
+            }
+        );
+        assert_eq!(
+            example_ranges().with_gap_tolerance(4),
+            Ranges::new(vec![MyRange { start: 3, end: 20 }]).with_gap_tolerance(4)
+        );
+        assert_eq!(example_ranges().with_gap_tolerance(0), example_ranges());
+    }
+
+    /// Draws a value in `[low, high)` from one xorshift step of `seed`.
+    fn random_in(seed: &mut u64, low: usize, high: usize) -> usize {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        low + (*seed % (high - low) as u64) as usize
+    }
+
+    /// The linear scan which [Ranges::first_matching_index] replaced.
+    fn linear_first_matching_index(ranges: &Ranges, new: &MyRange) -> usize {
+        ranges
+            .ranges
+            .iter()
+            .position(|range| {
+                range.partial_cmp(new) != Some(std::cmp::Ordering::Less)
+                    || range.touches_within(new, ranges.gap_tolerance)
+            })
+            .unwrap_or(ranges.ranges.len())
+    }
+
+    /// Builds the expected set by marking every added value, then filling in every gap of at most
+    /// `gap` values between the marked runs.
+    fn model_ranges(added: &[MyRange], gap: usize, universe: usize) -> Ranges {
+        let mut covered = vec![false; universe];
+        for range in added {
+            covered[range.start..=range.end].fill(true);
+        }
+        let mut expected: Vec<MyRange> = Vec::new();
+        for n in (0..universe).filter(|n| covered[*n]) {
+            match expected.last_mut() {
+                Some(last) if n - last.end <= gap + 1 => last.end = n,
+                _ => expected.push(MyRange { start: n, end: n }),
+            }
+        }
+        Ranges::new(expected).with_gap_tolerance(gap)
+    }
+
+    #[test]
+    fn test_add_range_matches_model() {
+        let mut seed: u64 = 0x2025_1205;
+        for _ in 0..500 {
+            let gap = random_in(&mut seed, 0, 4);
+            let mut ranges = Ranges::new(Vec::new()).with_gap_tolerance(gap);
+            let mut added = Vec::new();
+            for _ in 0..random_in(&mut seed, 0, 30) {
+                let start = random_in(&mut seed, 0, 200);
+                let new = MyRange {
+                    start,
+                    end: start + random_in(&mut seed, 0, 10),
+                };
+                assert_eq!(
+                    ranges.first_matching_index(&new),
+                    linear_first_matching_index(&ranges, &new)
+                );
+                added.push(MyRange {
+                    start: new.start,
+                    end: new.end,
+                });
+                ranges.add_range(new);
+            }
+            assert_eq!(ranges, model_ranges(&added, gap, 210));
+        }
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture` to see the timing. Locating the
+    /// insertion point is logarithmic, so descending order is dominated by shifting the vector.
+    #[test]
+    #[ignore]
+    fn bench_add_range() {
+        const N: usize = 50_000;
+        for (name, order) in [
+            ("ascending", (0..N).collect::<Vec<_>>()),
+            ("descending", (0..N).rev().collect()),
+        ] {
+            let start = std::time::Instant::now();
+            let mut ranges = Ranges::with_capacity(N);
+            for i in order {
+                ranges.add_range(MyRange {
+                    start: i * 10,
+                    end: i * 10 + 5,
+                });
+            }
+            assert_eq!(ranges.num_ranges(), N);
+            println!("add_range {name} x{N}: {:?}", start.elapsed());
+        }
+    }
+
+    #[test]
+    fn test_add_range_bridges_adjacent() {
+        let mut ranges = example_ranges();
+        ranges.add_range(MyRange { start: 6, end: 9 });
+        assert_eq!(ranges, Ranges::new(vec![MyRange { start: 3, end: 20 }]));
+    }
+
+    #[test]
+    fn test_count_fresh_detailed() {
+        let input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let (available, all) = count_fresh_detailed(input);
+        assert_eq!((available, all), (vec![5, 11, 17], 14));
+    }
+
     const SINGLETON_INPUT: &str = "
 3-5
 10-10
@@ -248,7 +1463,7 @@ mod tests {
         let ranges = Ranges::from(RANGE_INPUT.lines().map(|s| s.to_string()));
         assert_eq!(
             ranges,
-            Ranges(vec![
+            Ranges::new(vec![
                 MyRange {
                     start: 13873831532241,
                     end: 16714933495213
@@ -334,7 +1549,7 @@ mod tests {
         let ranges = Ranges::from(RANGE_INPUT_SORTED.lines().map(|s| s.to_string()));
         assert_eq!(
             ranges,
-            Ranges(vec![
+            Ranges::new(vec![
                 MyRange {
                     start: 13873831532241,
                     end: 16714933495213
@@ -414,4 +1629,30 @@ mod tests {
             ])
         )
     }
+
+    #[test]
+    fn test_ranges_from_matches_add_range() {
+        let adjacent = "\n1-2\n3-4\n6-8\n20-30\n25-26\n31-31\n";
+        for input in [RANGE_INPUT, RANGE_INPUT_SORTED, EXAMPLE_INPUT, adjacent] {
+            let fast = Ranges::from(input.lines().map(|s| s.to_string()));
+            let slow = input
+                .lines()
+                .skip_while(|line| line.is_empty())
+                .take_while(|line| !line.is_empty())
+                .fold(Ranges::with_capacity(0), |mut acc, line| {
+                    acc.add_range(MyRange::from_str(line).unwrap());
+                    acc
+                });
+            assert_eq!(fast, slow);
+        }
+        let ranges = Ranges::from(adjacent.lines().map(|s| s.to_string()));
+        assert_eq!(
+            ranges,
+            Ranges::new(vec![
+                MyRange { start: 1, end: 4 },
+                MyRange { start: 6, end: 8 },
+                MyRange { start: 20, end: 31 }
+            ])
+        );
+    }
 }
@@ -2,17 +2,64 @@ use std::io::Read;
 use std::num::ParseIntError;
 use std::str::FromStr;
 
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, PartialEq)]
 enum ParseNumsOrOpsError {
     ParseNum(ParseIntError),
     ParseOp,
     ParseNeither,
     ParseEmpty,
+    ParseNotIntegral,
+    ParseOverflow,
+    ParseUnexpected { row: usize, col: usize, byte: u8 },
+    ParseConflictingOps { column: usize },
+    ParseMissingOp { column: usize },
+    ParseMultipleRulers { row: usize },
 }
 
-#[derive(Debug)]
+/// Parses a number which may contain `_` separators, such as `1_000`, or be written in scientific
+/// notation, such as `1e3` or `1.5e3`. Scientific notation must produce an integral result.
+fn parse_number(s: &str) -> Result<usize, ParseNumsOrOpsError> {
+    let s = s.replace('_', "");
+    let Some((mantissa, exp)) = s.split_once(['e', 'E']) else {
+        return s.parse().map_err(ParseNumsOrOpsError::ParseNum);
+    };
+    let exp: u32 = exp.parse().map_err(ParseNumsOrOpsError::ParseNum)?;
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let frac_part = frac_part.trim_end_matches('0');
+    let shift = exp
+        .checked_sub(frac_part.len() as u32)
+        .ok_or(ParseNumsOrOpsError::ParseNotIntegral)?;
+    let digits: usize = format!("{int_part}{frac_part}")
+        .parse()
+        .map_err(ParseNumsOrOpsError::ParseNum)?;
+    10_usize
+        .checked_pow(shift)
+        .and_then(|scale| digits.checked_mul(scale))
+        .ok_or(ParseNumsOrOpsError::ParseOverflow)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Op {
     Add,
     Mul,
+    Sub,
+    Div,
+    Count,
+    Concat,
+}
+
+impl std::fmt::Display for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Op::Add => write!(f, "+"),
+            Op::Mul => write!(f, "*"),
+            Op::Sub => write!(f, "-"),
+            Op::Div => write!(f, "/"),
+            Op::Count => write!(f, "#"),
+            Op::Concat => write!(f, "|"),
+        }
+    }
 }
 
 impl FromStr for Op {
@@ -22,11 +69,111 @@ impl FromStr for Op {
         match s {
             "+" => Ok(Op::Add),
             "*" => Ok(Op::Mul),
+            "-" => Ok(Op::Sub),
+            "/" => Ok(Op::Div),
+            "#" => Ok(Op::Count),
+            "|" => Ok(Op::Concat),
             _ => Err(ParseNumsOrOpsError::ParseOp),
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ComputeError {
+    Underflow,
+    Overflow,
+    Remainder,
+    DivideByZero,
+}
+
+/// Controls how [Op::Sub] behaves when a column's result would go below zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum SubMode {
+    Saturating,
+    Wrapping,
+    #[default]
+    Checked,
+}
+
+impl FromStr for SubMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "saturating" => Ok(SubMode::Saturating),
+            "wrapping" => Ok(SubMode::Wrapping),
+            "checked" => Ok(SubMode::Checked),
+            _ => Err(()),
+        }
+    }
+}
+
+impl SubMode {
+    fn sub(self, lhs: usize, rhs: usize) -> Result<usize, ComputeError> {
+        match self {
+            SubMode::Saturating => Ok(lhs.saturating_sub(rhs)),
+            SubMode::Wrapping => Ok(lhs.wrapping_sub(rhs)),
+            SubMode::Checked => lhs.checked_sub(rhs).ok_or(ComputeError::Underflow),
+        }
+    }
+}
+
+/// Controls how [Op::Div] behaves when a division leaves a remainder.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum DivMode {
+    #[default]
+    Exact,
+    Floor,
+    Ceil,
+    Round,
+}
+
+impl FromStr for DivMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(DivMode::Exact),
+            "floor" => Ok(DivMode::Floor),
+            "ceil" => Ok(DivMode::Ceil),
+            "round" => Ok(DivMode::Round),
+            _ => Err(()),
+        }
+    }
+}
+
+impl DivMode {
+    /// Divides, rounding according to the mode. [DivMode::Round] rounds halves up.
+    fn div(self, lhs: usize, rhs: usize) -> Result<usize, ComputeError> {
+        if rhs == 0 {
+            return Err(ComputeError::DivideByZero);
+        }
+        let (quotient, remainder) = (lhs / rhs, lhs % rhs);
+        match self {
+            DivMode::Exact if remainder != 0 => Err(ComputeError::Remainder),
+            DivMode::Ceil if remainder != 0 => Ok(quotient + 1),
+            DivMode::Round if remainder >= rhs - remainder => Ok(quotient + 1),
+            _ => Ok(quotient),
+        }
+    }
+}
+
+/// The configurable semantics used when computing the result of a column.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ComputeOptions {
+    sub_mode: SubMode,
+    div_mode: DivMode,
+    /// Restricts [vertical_math], [columnar_math], [named_columnar_math], and [inline_math] to the
+    /// columns with this operator.
+    only_op: Option<Op>,
+}
+
+impl ComputeOptions {
+    fn includes(&self, sem_col: &SemanticColumn) -> bool {
+        self.only_op.is_none_or(|op| sem_col.op == op)
+    }
+}
+
 enum NumsOrOps {
     Nums(Vec<usize>),
     Ops(Vec<Op>),
@@ -48,7 +195,7 @@ impl FromStr for NumsOrOps {
 
 impl NumsOrOps {
     fn new(first_val: &str) -> Result<Self, ParseNumsOrOpsError> {
-        if let Ok(num) = first_val.parse::<usize>() {
+        if let Ok(num) = parse_number(first_val) {
             Ok(NumsOrOps::Nums(vec![num]))
         } else if let Ok(op) = first_val.parse::<Op>() {
             Ok(NumsOrOps::Ops(vec![op]))
@@ -59,10 +206,7 @@ impl NumsOrOps {
 
     fn add(&mut self, val: &str) -> Result<(), ParseNumsOrOpsError> {
         match self {
-            NumsOrOps::Nums(nums) => nums.push(
-                val.parse::<usize>()
-                    .map_err(ParseNumsOrOpsError::ParseNum)?,
-            ),
+            NumsOrOps::Nums(nums) => nums.push(parse_number(val)?),
             NumsOrOps::Ops(ops) => ops.push(val.parse::<Op>()?),
         }
         Ok(())
@@ -76,12 +220,34 @@ impl NumsOrOps {
     }
 }
 
-fn vertical_math(r: impl std::io::BufRead) -> impl Iterator<Item = usize> {
-    let mut cols: Vec<Vec<usize>> = Vec::new();
-    r.lines()
+fn vertical_math(
+    r: impl std::io::BufRead,
+    options: ComputeOptions,
+) -> impl Iterator<Item = Result<usize, ComputeError>> {
+    vertical_columns(r)
+        .filter(move |sem_col| options.includes(sem_col))
+        .map(move |sem_col| sem_col.compute(options))
+}
+
+/// Reads the [SemanticColumn]s of a grid whose rows are whitespace-separated numbers, followed by a
+/// row of operators.
+/// If the operator row has exactly one operator, it is broadcast to every column.
+///
+/// A ruler row, as read by [GridReader], is dropped rather than read as a row of [Op::Concat]
+/// operators, unless it is the last row.
+fn vertical_columns(r: impl std::io::BufRead) -> impl Iterator<Item = SemanticColumn> {
+    let rows: Vec<String> = r
+        .lines()
         .map_while(Result::ok)
         .filter(|line| !line.is_empty())
-        .map(|line| NumsOrOps::from_str(&line))
+        .collect();
+    let last = rows.len().saturating_sub(1);
+    let mut cols: Vec<Vec<usize>> = Vec::new();
+    let mut ops = rows
+        .into_iter()
+        .enumerate()
+        .filter(|(i, row)| *i == last || !is_ruler_row(row))
+        .map(|(_, line)| NumsOrOps::from_str(&line))
         .filter_map(Result::ok)
         .find_map(|row| {
             while cols.len() < row.len() {
@@ -98,13 +264,95 @@ fn vertical_math(r: impl std::io::BufRead) -> impl Iterator<Item = usize> {
                 NumsOrOps::Ops(ops) => Some(ops),
             }
         })
-        .unwrap()
-        .into_iter()
+        .unwrap();
+    if let [op] = ops[..] {
+        ops = vec![op; cols.len()];
+    }
+    ops.into_iter()
         .zip(cols)
-        .map(|(op, col)| match op {
-            Op::Add => col.into_iter().sum(),
-            Op::Mul => col.into_iter().product(),
+        .map(|(op, nums)| SemanticColumn { nums, op })
+}
+
+/// Parses a row of numbers followed by a single trailing operator, which can then be reduced like
+/// a column.
+fn parse_row(s: &str) -> Result<SemanticColumn, ParseNumsOrOpsError> {
+    let mut vals: Vec<&str> = s.split_whitespace().collect();
+    let op = vals.pop().ok_or(ParseNumsOrOpsError::ParseEmpty)?.parse()?;
+    let nums = vals
+        .into_iter()
+        .map(parse_number)
+        .collect::<Result<Vec<usize>, _>>()?;
+    Ok(SemanticColumn { nums, op })
+}
+
+/// Computes each row parsed by [parse_row], skipping any row which cannot be parsed. Rows which
+/// fail to compute, such as by underflowing, are yielded as errors rather than skipped.
+#[cfg_attr(not(test), allow(dead_code))]
+fn row_math(r: impl std::io::BufRead) -> impl Iterator<Item = Result<usize, ComputeError>> {
+    r.lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.is_empty())
+        .map(|line| parse_row(&line))
+        .filter_map(Result::ok)
+        .map(|row| row.compute(ComputeOptions::default()))
+}
+
+/// Parses a number with an optional trailing operator, such as `123+`.
+fn parse_inline_cell(s: &str) -> Result<(usize, Option<Op>), ParseNumsOrOpsError> {
+    if let Some((num, op)) = s.split_at_checked(s.len().saturating_sub(1))
+        && !num.is_empty()
+        && let Ok(op) = op.parse::<Op>()
+    {
+        return Ok((parse_number(num)?, Some(op)));
+    }
+    Ok((parse_number(s)?, None))
+}
+
+/// Parses rows of whitespace-separated cells where each number may carry its column's operator as
+/// a suffix, like `123+`, rather than having a separate operator row. Cells without a suffix are
+/// allowed, but every column must have at least one operator, and all of a column's operators
+/// must agree.
+fn parse_inline_columns(
+    r: impl std::io::BufRead,
+) -> Result<Vec<SemanticColumn>, ParseNumsOrOpsError> {
+    let mut nums: Vec<Vec<usize>> = Vec::new();
+    let mut ops: Vec<Option<Op>> = Vec::new();
+    for line in r.lines().map_while(Result::ok) {
+        for (column, cell) in line.split_whitespace().enumerate() {
+            if column == nums.len() {
+                nums.push(Vec::new());
+                ops.push(None);
+            }
+            let (num, op) = parse_inline_cell(cell)?;
+            nums[column].push(num);
+            match (&ops[column], op) {
+                (Some(existing), Some(op)) if *existing != op => {
+                    return Err(ParseNumsOrOpsError::ParseConflictingOps { column });
+                }
+                (None, op) => ops[column] = op,
+                _ => {}
+            }
+        }
+    }
+    nums.into_iter()
+        .zip(ops)
+        .enumerate()
+        .map(|(column, (nums, op))| match op {
+            Some(op) => Ok(SemanticColumn { nums, op }),
+            None => Err(ParseNumsOrOpsError::ParseMissingOp { column }),
         })
+        .collect()
+}
+
+fn inline_math(
+    r: impl std::io::BufRead,
+    options: ComputeOptions,
+) -> Result<impl Iterator<Item = Result<usize, ComputeError>>, ParseNumsOrOpsError> {
+    let columns = parse_inline_columns(r)?;
+    Ok(columns
+        .into_iter()
+        .filter(move |sem_col| options.includes(sem_col))
+        .map(move |sem_col| sem_col.compute(options)))
 }
 
 struct RawColumn {
@@ -112,99 +360,710 @@ struct RawColumn {
     op: Option<Op>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 struct SemanticColumn {
     nums: Vec<usize>,
     op: Op,
 }
 
 impl SemanticColumn {
-    fn compute(&self) -> usize {
+    /// Reduces the column using its operator. Addition and multiplication return
+    /// [ComputeError::Overflow] if the result does not fit in a [usize]. Subtraction and division
+    /// apply every subsequent number to the first, handling underflow and remainders according to
+    /// the given options.
+    /// Counting ignores the values and returns how many numbers the column has. Concatenation joins
+    /// the decimal digits of the numbers in order, so `12` and `34` become `1234`.
+    fn compute(&self, options: ComputeOptions) -> Result<usize, ComputeError> {
+        let Some((first, rest)) = self.nums.split_first() else {
+            return Ok(0);
+        };
         match self.op {
-            Op::Add => self.nums.iter().sum(),
-            Op::Mul => self.nums.iter().product(),
+            Op::Add => self
+                .nums
+                .iter()
+                .try_fold(0_usize, |acc, num| acc.checked_add(*num))
+                .ok_or(ComputeError::Overflow),
+            Op::Mul => self
+                .nums
+                .iter()
+                .try_fold(1_usize, |acc, num| acc.checked_mul(*num))
+                .ok_or(ComputeError::Overflow),
+            Op::Sub => rest
+                .iter()
+                .try_fold(*first, |acc, num| options.sub_mode.sub(acc, *num)),
+            Op::Div => rest
+                .iter()
+                .try_fold(*first, |acc, num| options.div_mode.div(acc, *num)),
+            Op::Count => Ok(self.nums.len()),
+            Op::Concat => concat(&self.nums),
+        }
+    }
+}
+
+impl SemanticColumn {
+    /// Returns true if computing the column would overflow a [usize], without computing the full
+    /// result. Only addition and multiplication are considered, and they are accumulated as a
+    /// [u128], stopping as soon as the result exceeds [usize::MAX].
+    fn overflow_risk(&self) -> bool {
+        let combine = match self.op {
+            Op::Add => u128::checked_add,
+            Op::Mul => u128::checked_mul,
+            Op::Sub | Op::Div | Op::Count | Op::Concat => return false,
+        };
+        let mut acc: u128 = match self.op {
+            Op::Mul => 1,
+            _ => 0,
+        };
+        for num in &self.nums {
+            match combine(acc, *num as u128) {
+                Some(result) if result <= usize::MAX as u128 => acc = result,
+                _ => return true,
+            }
         }
+        false
     }
 }
 
+impl SemanticColumn {
+    /// Returns the `(min, max, sum, product)` of the column's numbers, regardless of its
+    /// operator. The product is accumulated as a [u128], and both the sum and the product
+    /// saturate rather than overflow. An empty column has all four stats zero, matching
+    /// [SemanticColumn::compute].
+    fn column_stats(&self) -> (usize, usize, usize, u128) {
+        if self.nums.is_empty() {
+            return (0, 0, 0, 0);
+        }
+        self.nums.iter().fold(
+            (usize::MAX, usize::MIN, 0, 1),
+            |(min, max, sum, product), &num| {
+                (
+                    min.min(num),
+                    max.max(num),
+                    sum.saturating_add(num),
+                    product.saturating_mul(num as u128),
+                )
+            },
+        )
+    }
+}
+
+/// Concatenates the decimal digits of the numbers, building the result as a [u128] so that
+/// overflow is detected before narrowing it back to a [usize].
+fn concat(nums: &[usize]) -> Result<usize, ComputeError> {
+    nums.iter()
+        .try_fold(0_u128, |acc, &num| {
+            let digits = num.checked_ilog10().unwrap_or(0) + 1;
+            acc.checked_mul(10_u128.pow(digits))
+                .and_then(|shifted| shifted.checked_add(num as u128))
+        })
+        .and_then(|result| usize::try_from(result).ok())
+        .ok_or(ComputeError::Overflow)
+}
+
+/// Returns true if the row is a line drawn entirely of `-` or `=` characters, ignoring surrounding
+/// whitespace. A lone `-` is an operator rather than a separator, so a separator made only of `-`
+/// must be at least two characters wide.
+fn is_separator_row(row: &str) -> bool {
+    let row = row.trim();
+    row.chars().all(|c| c == '-' || c == '=') && (row.len() > 1 || row == "=")
+}
+
 /// [GridReader] is an iterator over the [SemanticColumn]s in a grid.
+///
+/// Each character column is read top-to-bottom as a single number. Since digits are never grouped
+/// horizontally, `_` separators and `e` exponent markers carry no meaning and are ignored like
+/// spaces, so a column reading `1`, `e`, `3` is 13. Both are only read within the numbers of a
+/// horizontal reader, where an exponent must sit inside its number's row.
+///
+/// Any other unrecognized byte is ignored, unless the reader is strict, in which case it yields an
+/// error reporting the byte's line within the input, counting from 0 and including any blank,
+/// separator, or ruler lines, and its column.
+///
+/// A column with numbers but no operator, such as one beyond the end of a short operator row,
+/// takes the `default_op` if one is set, and otherwise yields an error.
+///
+/// A horizontal reader instead reads the digits along each row of a column as one number, such as
+/// right-aligned multi-digit numbers. Each column then spans from its operator up to the next
+/// operator in the last row, so the operators must sit at the left edge of their columns.
+/// Whitespace within a row ends a number, so `1 2` in one column is read as 1 and 2 rather than 12.
+///
+/// Column boundaries may instead be given explicitly by a ruler row of `|`s, such as `   |   |`,
+/// anywhere above the operator row. Each column then spans everything between two pipes, even
+/// across blank character columns, and the character columns under the pipes are not read. Pipes
+/// may also mark the outer edges, such as `|   |   |`. Since a grid has at most one ruler, a second
+/// ruler row yields an error rather than being read as data.
 struct GridReader {
     width: usize,
     curr_col: usize,
+    column: usize,     // index of the next semantic column
     grid: Vec<String>, // for simplicity, split and own
+    lines: Vec<usize>, // the input line of each row of the grid
+    strict: bool,
+    horizontal: bool,
+    ruler: Option<Vec<usize>>, // positions of the pipes in the ruler row
+    error: Option<ParseNumsOrOpsError>, // reported before any column
+    broadcast: Option<Op>,
+    default_op: Option<Op>,
 }
 
 impl GridReader {
     fn new(r: impl std::io::BufRead) -> Self {
-        let rows: Vec<String> = r
+        let rows: Vec<(usize, String)> = r
             .lines()
             .map_while(Result::ok)
-            .filter(|line| !line.is_empty())
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
             .collect();
+        Self::from_rows(rows)
+    }
+
+    /// Separator rows are dropped, so that their `-` characters are not read as [Op::Sub].
+    ///
+    /// If the operator row has exactly one operator, it is broadcast to every column.
+    ///
+    /// Each row is given along with its line number in the input, which is used to report errors.
+    fn from_rows(mut rows: Vec<(usize, String)>) -> Self {
+        rows.retain(|(_, row)| !is_separator_row(row));
+        // The last row holds the operators, which may themselves be `|`s.
+        let mut ruler_rows = rows[..rows.len().saturating_sub(1)]
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, row))| is_ruler_row(row))
+            .map(|(i, _)| i);
+        let first_ruler = ruler_rows.next();
+        let error = ruler_rows
+            .next()
+            .map(|i| ParseNumsOrOpsError::ParseMultipleRulers { row: rows[i].0 });
+        let ruler = first_ruler.map(|i| {
+            rows.remove(i)
+                .1
+                .bytes()
+                .enumerate()
+                .filter(|(_, b)| *b == b'|')
+                .map(|(pos, _)| pos)
+                .collect()
+        });
+        let (lines, grid): (Vec<usize>, Vec<String>) = rows.into_iter().unzip();
         GridReader {
-            width: rows.iter().map(|r| r.len()).max().unwrap(),
+            width: grid.iter().map(|r| r.len()).max().unwrap_or(0),
             curr_col: 0,
-            grid: rows,
+            column: 0,
+            broadcast: grid.last().and_then(|row| broadcast_op(row)),
+            grid,
+            lines,
+            strict: false,
+            horizontal: false,
+            ruler,
+            error,
+            default_op: None,
         }
     }
 
-    fn next_raw_column(&mut self) -> Option<RawColumn> {
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    fn with_horizontal(mut self, horizontal: bool) -> Self {
+        self.horizontal = horizontal;
+        self
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn with_default_op(mut self, default_op: Option<Op>) -> Self {
+        self.default_op = default_op;
+        self
+    }
+
+    fn next_raw_column(&mut self) -> Result<Option<RawColumn>, ParseNumsOrOpsError> {
         if self.curr_col >= self.width {
-            return None;
+            return Ok(None);
         }
-        let mut pos = self.curr_col;
+        let pos = self.curr_col;
         self.curr_col += 1;
         let mut digits = String::new();
         let mut op: Option<Op> = None;
-        for row in &self.grid {
+        for (i, row) in self.grid.iter().enumerate() {
             let Some(c) = row.as_bytes().get(pos) else {
                 continue;
             };
+            if let Some(o) = op_from_byte(*c) {
+                op = Some(o);
+                continue;
+            }
             match c {
                 b'0'..=b'9' => digits.push((*c).into()),
-                b'+' => op = Some(Op::Add),
-                b'*' => op = Some(Op::Mul),
-                _ => {} // ignore it
+                b'_' | b'e' | b'E' => {}
+                c if !self.strict || c.is_ascii_whitespace() => {} // ignore it
+                c => {
+                    return Err(ParseNumsOrOpsError::ParseUnexpected {
+                        row: self.lines[i],
+                        col: pos,
+                        byte: *c,
+                    });
+                }
             }
         }
         if digits.is_empty() {
-            return None;
+            return Ok(None);
+        }
+        let num: usize = parse_number(&digits)?;
+        Ok(Some(RawColumn { num, op }))
+    }
+
+    /// If the grid has a ruler, skips past any pipes at the current position and returns the end of
+    /// the column which starts there.
+    fn next_ruled_span(&mut self) -> Option<usize> {
+        let pipes = self.ruler.as_ref()?;
+        while pipes.contains(&self.curr_col) {
+            self.curr_col += 1;
+        }
+        let end = pipes.iter().copied().find(|&pos| pos > self.curr_col);
+        Some(end.unwrap_or(self.width).min(self.width))
+    }
+
+    /// Reads the next column of a horizontal reader, spanning up to the given end, or otherwise
+    /// from the next operator in the last row up to the operator after it.
+    fn next_horizontal_column(
+        &mut self,
+        end: Option<usize>,
+    ) -> Result<(Vec<usize>, Option<Op>), ParseNumsOrOpsError> {
+        let start = self.curr_col;
+        let op_row = self.grid.last().map_or(&[][..], |row| row.as_bytes());
+        let is_op_at = |i: &usize| op_row.get(*i).is_some_and(|b| op_from_byte(*b).is_some());
+        let end = end.unwrap_or_else(|| {
+            let first_op = (start..self.width).find(is_op_at).unwrap_or(start);
+            (first_op + 1..self.width)
+                .find(is_op_at)
+                .unwrap_or(self.width)
+        });
+        self.curr_col = end;
+        let mut nums: Vec<usize> = Vec::new();
+        let mut op: Option<Op> = None;
+        for (i, row) in self.grid.iter().enumerate() {
+            let bytes = row.as_bytes();
+            let span = &bytes[start.min(bytes.len())..end.min(bytes.len())];
+            let mut digits = String::new();
+            for (offset, c) in span.iter().enumerate() {
+                if let Some(o) = op_from_byte(*c) {
+                    op = Some(o);
+                    continue;
+                }
+                match c {
+                    b'0'..=b'9' | b'e' | b'E' | b'_' => digits.push((*c).into()),
+                    c if c.is_ascii_whitespace() => {
+                        if !digits.is_empty() {
+                            nums.push(parse_number(&std::mem::take(&mut digits))?);
+                        }
+                    }
+                    _ if !self.strict => {} // ignore it
+                    c => {
+                        return Err(ParseNumsOrOpsError::ParseUnexpected {
+                            row: self.lines[i],
+                            col: start + offset,
+                            byte: *c,
+                        });
+                    }
+                }
+            }
+            if !digits.is_empty() {
+                nums.push(parse_number(&digits)?);
+            }
         }
-        let num: usize = digits.parse().unwrap();
-        Some(RawColumn { num, op })
+        Ok((nums, op))
+    }
+}
+
+/// Returns true if the row is a ruler made only of `|`s and spaces.
+fn is_ruler_row(row: &str) -> bool {
+    row.contains('|') && row.bytes().all(|b| b == b'|' || b == b' ')
+}
+
+fn op_from_byte(b: u8) -> Option<Op> {
+    match b {
+        b'+' => Some(Op::Add),
+        b'*' => Some(Op::Mul),
+        b'-' => Some(Op::Sub),
+        b'/' => Some(Op::Div),
+        b'#' => Some(Op::Count),
+        b'|' => Some(Op::Concat),
+        _ => None,
     }
 }
 
 impl Iterator for GridReader {
-    type Item = SemanticColumn;
+    type Item = Result<SemanticColumn, ParseNumsOrOpsError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            self.curr_col = self.width;
+            return Some(Err(e));
+        }
+        let span_end = self.next_ruled_span();
         if self.curr_col >= self.width {
             return None;
         }
         let mut nums: Vec<usize> = Vec::new();
         let mut op: Option<Op> = None;
-        while let Some(raw_col) = self.next_raw_column() {
-            nums.push(raw_col.num);
-            op = op.or(raw_col.op);
+        if self.horizontal {
+            match self.next_horizontal_column(span_end) {
+                Ok(col) => (nums, op) = col,
+                Err(e) => return Some(Err(e)),
+            }
+        } else {
+            while span_end.is_none_or(|end| self.curr_col < end) {
+                match self.next_raw_column() {
+                    Ok(Some(raw_col)) => {
+                        nums.push(raw_col.num);
+                        op = op.or(raw_col.op);
+                    }
+                    // a blank column only ends an unruled column
+                    Ok(None) if span_end.is_some() => {}
+                    Ok(None) => break,
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+        }
+        if nums.is_empty() && op.is_none() {
+            return None;
+        }
+        let column = self.column;
+        self.column += 1;
+        match op.or(self.broadcast).or(self.default_op) {
+            Some(op) => Some(Ok(SemanticColumn { nums, op })),
+            None => Some(Err(ParseNumsOrOpsError::ParseMissingOp { column })),
+        }
+    }
+}
+
+/// Returns the operator of an operator row which consists of exactly one operator.
+fn broadcast_op(row: &str) -> Option<Op> {
+    match NumsOrOps::from_str(row) {
+        Ok(NumsOrOps::Ops(ops)) => match ops[..] {
+            [op] => Some(op),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Renders the columns back into a grid which [GridReader] reads as the same columns. Each number
+/// is written top-to-bottom in its own character column, aligned to the bottom so that its last
+/// digit sits just above the operator row, and adjacent columns are separated by a blank character
+/// column. A column without any numbers cannot be read back.
+#[cfg_attr(not(test), allow(dead_code))]
+fn render(columns: &[SemanticColumn]) -> String {
+    let height = columns
+        .iter()
+        .flat_map(|sem_col| &sem_col.nums)
+        .map(|num| num.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let mut rows = vec![String::new(); height + 1];
+    for (i, sem_col) in columns.iter().enumerate() {
+        if i > 0 {
+            rows.iter_mut().for_each(|row| row.push(' '));
         }
-        op.map(|o| SemanticColumn { nums, op: o })
+        for num in &sem_col.nums {
+            let digits = format!("{num:>height$}");
+            for (row, digit) in rows.iter_mut().zip(digits.chars()) {
+                row.push(digit);
+            }
+        }
+        let width = sem_col.nums.len().max(1);
+        if sem_col.nums.is_empty() {
+            rows[..height].iter_mut().for_each(|row| row.push(' '));
+        }
+        rows[height].push_str(&format!("{:<width$}", sem_col.op.to_string()));
     }
+    rows.iter()
+        .map(|row| row.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-fn columnar_math(r: impl std::io::BufRead) -> impl Iterator<Item = usize> {
+/// The shape of a grid, as parsed by [GridReader], without computing any column results.
+#[derive(Debug, PartialEq)]
+struct GridShape {
+    num_columns: usize,
+    num_number_rows: usize,
+    ops: Vec<Op>,
+}
+
+/// Returns the first error of any column which cannot be read, such as one without an operator.
+fn describe_grid(r: impl std::io::BufRead) -> Result<GridShape, ParseNumsOrOpsError> {
     let reader = GridReader::new(r);
-    reader.map(|sem_col| sem_col.compute())
+    let num_number_rows = reader
+        .grid
+        .iter()
+        .filter(|row| row.bytes().any(|b| b.is_ascii_digit()))
+        .count();
+    let ops: Vec<Op> = reader
+        .map(|sem_col| sem_col.map(|sem_col| sem_col.op))
+        .collect::<Result<_, _>>()?;
+    Ok(GridShape {
+        num_columns: ops.len(),
+        num_number_rows,
+        ops,
+    })
+}
+
+/// An error from [columnar_math], which may fail either to read a column from the grid, such as
+/// when its digits overflow a [usize], or to compute it.
+#[derive(Debug, PartialEq)]
+enum ColumnarError {
+    Parse(ParseNumsOrOpsError),
+    Compute(ComputeError),
+}
+
+impl From<ComputeError> for ColumnarError {
+    fn from(e: ComputeError) -> Self {
+        ColumnarError::Compute(e)
+    }
+}
+
+fn columnar_math(
+    r: impl std::io::BufRead,
+    options: ComputeOptions,
+) -> impl Iterator<Item = Result<usize, ColumnarError>> {
+    compute_columns(GridReader::new(r), options)
+}
+
+fn compute_columns(
+    reader: GridReader,
+    options: ComputeOptions,
+) -> impl Iterator<Item = Result<usize, ColumnarError>> {
+    reader
+        .filter(move |sem_col| !matches!(sem_col, Ok(sem_col) if !options.includes(sem_col)))
+        .map(move |sem_col| match sem_col {
+            Ok(sem_col) => Ok(sem_col.compute(options)?),
+            Err(e) => Err(ColumnarError::Parse(e)),
+        })
+}
+
+/// Like [columnar_math], but reads the grid with a horizontal [GridReader], so that the digits
+/// along each row of a column form one number.
+fn horizontal_math(
+    r: impl std::io::BufRead,
+    options: ComputeOptions,
+) -> impl Iterator<Item = Result<usize, ColumnarError>> {
+    compute_columns(GridReader::new(r).with_horizontal(true), options)
+}
+
+/// Like [columnar_math], but treats the first non-empty row as a header of whitespace-separated
+/// column names, which are associated with the columns in order. Columns beyond the end of the
+/// header are named by their index. A column which cannot be read yields its error under its name.
+fn named_columnar_math(
+    r: impl std::io::BufRead,
+    options: ComputeOptions,
+) -> impl Iterator<Item = (String, Result<usize, ColumnarError>)> {
+    let mut rows: Vec<(usize, String)> = r
+        .lines()
+        .map_while(Result::ok)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+    let names: Vec<String> = if rows.is_empty() {
+        Vec::new()
+    } else {
+        rows.remove(0)
+            .1
+            .split_whitespace()
+            .map(String::from)
+            .collect()
+    };
+    GridReader::from_rows(rows)
+        .enumerate()
+        .filter(move |(_, sem_col)| !matches!(sem_col, Ok(sem_col) if !options.includes(sem_col)))
+        .map(move |(i, sem_col)| {
+            let name = names.get(i).cloned().unwrap_or_else(|| i.to_string());
+            let result = match sem_col {
+                Ok(sem_col) => sem_col.compute(options).map_err(ColumnarError::Compute),
+                Err(e) => Err(ColumnarError::Parse(e)),
+            };
+            (name, result)
+        })
+}
+
+/// Sums the results as a [u128], so that many large results cannot wrap the total. Returns the
+/// first error among the results, or [ComputeError::Overflow] if even the [u128] total overflows.
+fn grand_total<E: From<ComputeError>>(
+    mut results: impl Iterator<Item = Result<usize, E>>,
+) -> Result<u128, E> {
+    results.try_fold(0_u128, |acc, result| {
+        acc.checked_add(result? as u128)
+            .ok_or(ComputeError::Overflow.into())
+    })
+}
+
+/// Writes each result on its own line as soon as it is computed, so that the results for very wide
+/// grids are never collected. Stops at and returns the first error.
+fn write_per_column<E>(
+    results: impl Iterator<Item = Result<usize, E>>,
+    w: &mut impl std::io::Write,
+) -> Result<(), E> {
+    for result in results {
+        writeln!(w, "{}", result?).expect("failed to write result");
+    }
+    Ok(())
+}
+
+/// How the results of a computation are printed.
+#[derive(Clone, Copy, PartialEq)]
+enum Output {
+    Labeled,
+    SumOnly,
+    PerColumn,
+}
+
+fn report<E: From<ComputeError> + std::fmt::Debug>(
+    name: &str,
+    results: impl Iterator<Item = Result<usize, E>>,
+    output: Output,
+) {
+    let outcome = match output {
+        Output::PerColumn => write_per_column(results, &mut std::io::stdout().lock()),
+        Output::SumOnly => grand_total(results).map(|total| println!("{total}")),
+        Output::Labeled => {
+            grand_total(results).map(|total| println!("Sum of {name} computations: {total}"))
+        }
+    };
+    if let Err(e) = outcome {
+        eprintln!("Error in {name} computations: {e:?}");
+    }
+}
+
+/// Prints the indices of the columns for which [SemanticColumn::overflow_risk] is true.
+fn report_overflow_risk(name: &str, columns: impl Iterator<Item = SemanticColumn>) {
+    let risky: Vec<String> = columns
+        .enumerate()
+        .filter(|(_, sem_col)| sem_col.overflow_risk())
+        .map(|(i, _)| i.to_string())
+        .collect();
+    println!("Overflow-prone {name} columns: {}", risky.join(" "));
+}
+
+/// Prints the [SemanticColumn::column_stats] of every column.
+fn report_stats(name: &str, columns: impl Iterator<Item = SemanticColumn>) {
+    for (i, sem_col) in columns.enumerate() {
+        let (min, max, sum, product) = sem_col.column_stats();
+        println!("{name} column {i}: min {min} max {max} sum {sum} product {product}");
+    }
+}
+
+/// Parses the value following the given flag, exiting with the usage message if the value is
+/// missing or invalid. Returns the default if the flag is not present.
+fn parse_flag<T: FromStr + Default>(args: &[String], flag: &str, usage: &str) -> T {
+    match args.iter().position(|arg| arg == flag) {
+        Some(i) => args
+            .get(i + 1)
+            .and_then(|val| val.parse().ok())
+            .unwrap_or_else(|| {
+                eprintln!("{usage}");
+                std::process::exit(1);
+            }),
+        None => T::default(),
+    }
 }
 
 fn main() {
     let mut input_buf = Vec::new();
     std::io::stdin().lock().read_to_end(&mut input_buf).unwrap();
     let complete_input = String::from_utf8(input_buf).unwrap();
-    let standard: usize = vertical_math(std::io::BufReader::new(complete_input.as_bytes())).sum();
-    println!("Sum of standard computations: {standard}");
-    let columnar: usize = columnar_math(std::io::BufReader::new(complete_input.as_bytes())).sum();
-    println!("Sum of columnar computations: {columnar}");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = ComputeOptions {
+        sub_mode: parse_flag(
+            &args,
+            "--sub-mode",
+            "--sub-mode must be one of: saturating, wrapping, checked",
+        ),
+        div_mode: parse_flag(
+            &args,
+            "--div-mode",
+            "--div-mode must be one of: exact, floor, ceil, round",
+        ),
+        only_op: args.iter().position(|arg| arg == "--only-op").map(|i| {
+            args.get(i + 1)
+                .and_then(|val| val.parse().ok())
+                .unwrap_or_else(|| {
+                    eprintln!("--only-op must be one of: + * - / # |");
+                    std::process::exit(1);
+                })
+        }),
+    };
+    if args.iter().any(|arg| arg == "--describe") {
+        let shape = match describe_grid(std::io::BufReader::new(complete_input.as_bytes())) {
+            Ok(shape) => shape,
+            Err(e) => {
+                eprintln!("Error describing grid: {e:?}");
+                return;
+            }
+        };
+        let ops: Vec<String> = shape.ops.iter().map(Op::to_string).collect();
+        println!("Columns: {}", shape.num_columns);
+        println!("Number rows: {}", shape.num_number_rows);
+        println!("Operators: {}", ops.join(" "));
+        return;
+    }
+    if args.iter().any(|arg| arg == "--check") {
+        let standard_input = std::io::BufReader::new(complete_input.as_bytes());
+        report_overflow_risk("standard", vertical_columns(standard_input));
+        let columnar_input = std::io::BufReader::new(complete_input.as_bytes());
+        match GridReader::new(columnar_input).collect::<Result<Vec<_>, _>>() {
+            Ok(columnar) => report_overflow_risk("columnar", columnar.into_iter()),
+            Err(e) => eprintln!("Error reading columnar columns: {e:?}"),
+        }
+        return;
+    }
+    if args.iter().any(|arg| arg == "--stats") {
+        let standard_input = std::io::BufReader::new(complete_input.as_bytes());
+        report_stats("standard", vertical_columns(standard_input));
+        let columnar_input = std::io::BufReader::new(complete_input.as_bytes());
+        match GridReader::new(columnar_input).collect::<Result<Vec<_>, _>>() {
+            Ok(columnar) => report_stats("columnar", columnar.into_iter()),
+            Err(e) => eprintln!("Error reading columnar columns: {e:?}"),
+        }
+        return;
+    }
+    if args.iter().any(|arg| arg == "--named") {
+        let named_input = std::io::BufReader::new(complete_input.as_bytes());
+        for (name, result) in named_columnar_math(named_input, options) {
+            match result {
+                Ok(value) => println!("{name}: {value}"),
+                Err(e) => eprintln!("Error in column {name}: {e:?}"),
+            }
+        }
+        return;
+    }
+    let output = if args.iter().any(|arg| arg == "--per-column") {
+        Output::PerColumn
+    } else if args.iter().any(|arg| arg == "--sum-only") {
+        Output::SumOnly
+    } else {
+        Output::Labeled
+    };
+    if args.iter().any(|arg| arg == "--horizontal") {
+        let horizontal_input = std::io::BufReader::new(complete_input.as_bytes());
+        report(
+            "horizontal",
+            horizontal_math(horizontal_input, options),
+            output,
+        );
+        return;
+    }
+    if args.iter().any(|arg| arg == "--inline") {
+        let inline_input = std::io::BufReader::new(complete_input.as_bytes());
+        match inline_math(inline_input, options) {
+            Ok(results) => report("inline", results, output),
+            Err(e) => eprintln!("Error parsing inline operators: {e:?}"),
+        }
+        return;
+    }
+    let standard_input = std::io::BufReader::new(complete_input.as_bytes());
+    report("standard", vertical_math(standard_input, options), output);
+    let columnar_input = std::io::BufReader::new(complete_input.as_bytes());
+    report("columnar", columnar_math(columnar_input, options), output);
 }
 
 #[cfg(test)]
@@ -218,14 +1077,870 @@ mod tests {
     #[test]
     fn test_vertical_math() {
         let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
-        let result: Vec<usize> = super::vertical_math(test_input).collect();
-        assert_eq!(result, vec![33210, 490, 4243455, 401]);
+        let result: Result<Vec<usize>, _> =
+            super::vertical_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![33210, 490, 4243455, 401]));
+    }
+
+    #[test]
+    fn test_only_op() {
+        let options = super::ComputeOptions {
+            only_op: Some(super::Op::Mul),
+            ..Default::default()
+        };
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let result: Result<Vec<usize>, _> = super::vertical_math(test_input, options).collect();
+        assert_eq!(result, Ok(vec![33210, 4243455]));
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let result = super::grand_total(super::vertical_math(test_input, options));
+        assert_eq!(result, Ok(33210 + 4243455));
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let result = super::grand_total(super::columnar_math(test_input, options));
+        assert_eq!(result, Ok(8544 + 3253600));
+        let test_input = std::io::BufReader::new(INLINE_INPUT.as_bytes());
+        let result = super::grand_total(super::inline_math(test_input, options).unwrap());
+        assert_eq!(result, Ok(33210 + 4243455));
+        let named_input = format!("\nqty price tax total{EXAMPLE_INPUT}");
+        let test_input = std::io::BufReader::new(named_input.as_bytes());
+        let result: Vec<(String, Result<usize, _>)> =
+            super::named_columnar_math(test_input, options).collect();
+        assert_eq!(
+            result,
+            vec![
+                ("qty".to_string(), Ok(8544)),
+                ("tax".to_string(), Ok(3253600))
+            ]
+        );
+
+        let options = super::ComputeOptions {
+            only_op: Some(super::Op::Div),
+            ..Default::default()
+        };
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        assert_eq!(super::vertical_math(test_input, options).count(), 0);
+    }
+
+    #[test]
+    fn test_broadcast_op() {
+        let broadcast_input = EXAMPLE_INPUT.replace("*   +   *   +", "*");
+        let all_mul_input = EXAMPLE_INPUT.replace("*   +   *   +", "*   *   *   *");
+        let vertical = |input: &str| -> Result<Vec<usize>, _> {
+            super::vertical_math(
+                std::io::BufReader::new(input.as_bytes()),
+                Default::default(),
+            )
+            .collect()
+        };
+        let columnar = |input: &str| -> Result<Vec<usize>, _> {
+            super::columnar_math(
+                std::io::BufReader::new(input.as_bytes()),
+                Default::default(),
+            )
+            .collect()
+        };
+        assert_eq!(
+            vertical(&broadcast_input),
+            Ok(vec![33210, 2057216, 4243455, 462208])
+        );
+        assert_eq!(vertical(&broadcast_input), vertical(&all_mul_input));
+        assert_eq!(columnar(&broadcast_input).map(|v| v.len()), Ok(4));
+        assert_eq!(columnar(&broadcast_input), columnar(&all_mul_input));
+    }
+
+    const ROW_INPUT: &str = "
+1 2 3 +
+4 5 *
+ 10 *
+7 8   +";
+
+    #[test]
+    fn test_row_math() {
+        let test_input = std::io::BufReader::new(ROW_INPUT.as_bytes());
+        let result: Result<Vec<usize>, _> = super::row_math(test_input).collect();
+        assert_eq!(result, Ok(vec![6, 20, 10, 15]));
+
+        // the unparseable row is skipped, but the underflowing row is reported
+        let test_input = std::io::BufReader::new("1 2 +\n5 x -\n5 10 -\n3 1 -".as_bytes());
+        let result: Vec<_> = super::row_math(test_input).collect();
+        assert_eq!(
+            result,
+            vec![Ok(3), Err(super::ComputeError::Underflow), Ok(2)]
+        );
     }
 
     #[test]
     fn test_columnar_math() {
         let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
-        let result: Vec<usize> = super::columnar_math(test_input).collect();
-        assert_eq!(result, vec![8544, 625, 3253600, 1058]);
+        let result: Result<Vec<usize>, _> =
+            super::columnar_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![8544, 625, 3253600, 1058]));
+    }
+
+    #[test]
+    fn test_columnar_math_parse_overflow() {
+        use super::{ColumnarError, ParseNumsOrOpsError};
+        // The second column reads 21 nines, which is more than a usize can hold.
+        let overflow_input = format!("\n1 9\n2 9\n{}+ +", "  9\n".repeat(19));
+        let test_input = std::io::BufReader::new(overflow_input.as_bytes());
+        let mut results = super::columnar_math(test_input, Default::default());
+        assert_eq!(results.next(), Some(Ok(12)));
+        assert!(matches!(
+            results.next(),
+            Some(Err(ColumnarError::Parse(ParseNumsOrOpsError::ParseNum(_))))
+        ));
+
+        // Overflowing by scientific notation is reported too, though only a horizontal reader
+        // reads exponents.
+        let test_input = std::io::BufReader::new("1e25\n*".as_bytes());
+        let mut reader = super::GridReader::new(test_input).with_horizontal(true);
+        assert_eq!(reader.next(), Some(Err(ParseNumsOrOpsError::ParseOverflow)));
+    }
+
+    #[test]
+    fn test_named_columnar_math() {
+        let named_input = format!("\nqty price tax total{EXAMPLE_INPUT}");
+        let test_input = std::io::BufReader::new(named_input.as_bytes());
+        let result: Vec<(String, Result<usize, _>)> =
+            super::named_columnar_math(test_input, Default::default()).collect();
+        assert_eq!(
+            result,
+            vec![
+                ("qty".to_string(), Ok(8544)),
+                ("price".to_string(), Ok(625)),
+                ("tax".to_string(), Ok(3253600)),
+                ("total".to_string(), Ok(1058)),
+            ]
+        );
+
+        let short_header = format!("\nqty price{EXAMPLE_INPUT}");
+        let test_input = std::io::BufReader::new(short_header.as_bytes());
+        let names: Vec<String> = super::named_columnar_math(test_input, Default::default())
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["qty", "price", "2", "3"]);
+
+        // A header without any grid below it has no columns.
+        let test_input = std::io::BufReader::new("a b\n".as_bytes());
+        assert_eq!(
+            super::named_columnar_math(test_input, Default::default()).count(),
+            0
+        );
+
+        let named_input = format!("\nqty price tax total extra{SHORT_OPS_INPUT}");
+        let test_input = std::io::BufReader::new(named_input.as_bytes());
+        let result: Vec<(String, Result<usize, _>)> =
+            super::named_columnar_math(test_input, Default::default()).collect();
+        assert_eq!(result.len(), 5);
+        assert_eq!(
+            result[4],
+            (
+                "extra".to_string(),
+                Err(super::ColumnarError::Parse(
+                    super::ParseNumsOrOpsError::ParseMissingOp { column: 4 }
+                ))
+            )
+        );
+    }
+
+    const SEPARATED_INPUT: &str = "
+123 328  51 64
+ 45 64  387 23
+  6 98  215 314
+===============
+*   +   *   +";
+
+    #[test]
+    fn test_separator_row() {
+        for separated in [
+            SEPARATED_INPUT.to_string(),
+            SEPARATED_INPUT.replace('=', "-"),
+        ] {
+            let test_input = std::io::BufReader::new(separated.as_bytes());
+            let result: Result<Vec<usize>, _> =
+                super::columnar_math(test_input, Default::default()).collect();
+            assert_eq!(result, Ok(vec![8544, 625, 3253600, 1058]));
+
+            let test_input = std::io::BufReader::new(separated.as_bytes());
+            let result: Result<Vec<usize>, _> =
+                super::vertical_math(test_input, Default::default()).collect();
+            assert_eq!(result, Ok(vec![33210, 490, 4243455, 401]));
+        }
+
+        // Read as operators, the separator would make this column a subtraction.
+        let test_input = std::io::BufReader::new("12\n34\n--\n +".as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::columnar_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![37]));
+
+        assert!(super::is_separator_row("  ---  "));
+        assert!(super::is_separator_row("="));
+        assert!(!super::is_separator_row("-"));
+        assert!(!super::is_separator_row("- -"));
+        assert!(!super::is_separator_row("-  +"));
+    }
+
+    const SHORT_OPS_INPUT: &str = "
+123 328  51 64  7
+ 45 64  387 23  8
+  6 98  215 314 9
+*   +   *   +";
+
+    #[test]
+    fn test_missing_op_errors() {
+        use super::{GridReader, ParseNumsOrOpsError};
+        let reader = GridReader::new(std::io::BufReader::new(SHORT_OPS_INPUT.as_bytes()));
+        let result: Vec<_> = reader.collect();
+        assert_eq!(result.len(), 5);
+        assert!(result[..4].iter().all(|col| col.is_ok()));
+        assert!(matches!(
+            result[4],
+            Err(ParseNumsOrOpsError::ParseMissingOp { column: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_missing_op_default() {
+        use super::{GridReader, Op, SemanticColumn};
+        let reader = GridReader::new(std::io::BufReader::new(SHORT_OPS_INPUT.as_bytes()))
+            .with_default_op(Some(Op::Add));
+        let result: Vec<SemanticColumn> = reader.map(Result::unwrap).collect();
+        let last = result.last().unwrap();
+        assert_eq!((&last.nums, &last.op), (&vec![789], &Op::Add));
+        let result: Result<Vec<usize>, _> = result
+            .into_iter()
+            .map(|col| col.compute(Default::default()))
+            .collect();
+        assert_eq!(result, Ok(vec![8544, 625, 3253600, 1058, 789]));
+    }
+
+    const RIGHT_ALIGNED_INPUT: &str = "
+123 328  51  64
+ 45  64 387  23
+  6  98 215 314
+*   +   *   +";
+
+    #[test]
+    fn test_horizontal_grid_reader() {
+        use super::GridReader;
+        let reader = GridReader::new(std::io::BufReader::new(RIGHT_ALIGNED_INPUT.as_bytes()))
+            .with_horizontal(true);
+        let result: Vec<Vec<usize>> = reader.map(|col| col.unwrap().nums).collect();
+        assert_eq!(
+            result,
+            vec![
+                vec![123, 45, 6],
+                vec![328, 64, 98],
+                vec![51, 387, 215],
+                vec![64, 23, 314]
+            ]
+        );
+
+        // Horizontal reading agrees with the row-wise reading, regardless of alignment.
+        for input in [RIGHT_ALIGNED_INPUT, EXAMPLE_INPUT] {
+            let reader =
+                GridReader::new(std::io::BufReader::new(input.as_bytes())).with_horizontal(true);
+            let result: Result<Vec<usize>, _> = reader
+                .map(|col| col.unwrap().compute(Default::default()))
+                .collect();
+            assert_eq!(result, Ok(vec![33210, 490, 4243455, 401]));
+        }
+
+        // Operators need not start at the left edge of the grid.
+        let reader = GridReader::new(std::io::BufReader::new(" 12 3\n  4 5\n  * +".as_bytes()))
+            .with_horizontal(true);
+        let result: Vec<Vec<usize>> = reader.map(|col| col.unwrap().nums).collect();
+        assert_eq!(result, vec![vec![12, 4], vec![3, 5]]);
+
+        // Separate numbers on one row of a column are not glued together.
+        let test_input = std::io::BufReader::new("1 2 3\n4   5\n+   *".as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::horizontal_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![7, 15]));
+    }
+
+    const RULER_INPUT: &str = "
+   |   |
+1 2 34  5
+3 4 6 7 8
++   *   +";
+
+    #[test]
+    fn test_ruler_grid_reader() {
+        use super::{GridReader, ParseNumsOrOpsError};
+        let reader = GridReader::new(std::io::BufReader::new(RULER_INPUT.as_bytes()));
+        let result: Vec<Vec<usize>> = reader.map(|col| col.unwrap().nums).collect();
+        assert_eq!(result, vec![vec![13, 24], vec![36, 4, 7], vec![58]]);
+        let test_input = std::io::BufReader::new(RULER_INPUT.as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::columnar_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![37, 1008, 58]));
+
+        let reader =
+            GridReader::new(std::io::BufReader::new(RULER_INPUT.as_bytes())).with_horizontal(true);
+        let result: Vec<Vec<usize>> = reader.map(|col| col.unwrap().nums).collect();
+        assert_eq!(result, vec![vec![1, 2, 3, 4], vec![34, 6, 7], vec![5, 8]]);
+
+        // Without the ruler, the blank character columns split the columns apart.
+        let unruled = RULER_INPUT.replace("   |   |\n", "");
+        let mut reader = GridReader::new(std::io::BufReader::new(unruled.as_bytes()));
+        assert_eq!(reader.next().unwrap().unwrap().nums, vec![13]);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(ParseNumsOrOpsError::ParseMissingOp { column: 1 }))
+        ));
+
+        // The ruler also holds over the example, whose columns it agrees with.
+        let ruled = format!("\n   |   |   |{EXAMPLE_INPUT}");
+        let test_input = std::io::BufReader::new(ruled.as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::columnar_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![8544, 625, 3253600, 1058]));
+
+        // The standard reading ignores the ruler rather than reading it as operators.
+        let ruled = format!("\n   |   |   |{EXAMPLE_INPUT}");
+        let test_input = std::io::BufReader::new(ruled.as_bytes());
+        let result = super::grand_total(super::vertical_math(test_input, Default::default()));
+        assert_eq!(result, Ok(33210 + 490 + 4243455 + 401));
+
+        // Pipes may mark the outer edges too, including past the end of the longest row.
+        let edged = EXAMPLE_INPUT.replace('\n', "\n ");
+        let ruled = format!("\n|   |   |   |   |{edged}");
+        let test_input = std::io::BufReader::new(ruled.as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::columnar_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![8544, 625, 3253600, 1058]));
+
+        // A second ruler is reported rather than read as data.
+        let ruled = format!("\n|   |   |   |   |\n|   |   |   |   |{edged}");
+        let mut reader = GridReader::new(std::io::BufReader::new(ruled.as_bytes()));
+        assert_eq!(
+            reader.next(),
+            Some(Err(ParseNumsOrOpsError::ParseMultipleRulers { row: 2 }))
+        );
+        assert_eq!(reader.next(), None);
+    }
+
+    #[test]
+    fn test_render() {
+        use super::{GridReader, Op, SemanticColumn};
+        let columns: Vec<SemanticColumn> =
+            GridReader::new(std::io::BufReader::new(EXAMPLE_INPUT.as_bytes()))
+                .map(Result::unwrap)
+                .collect();
+        let rendered = super::render(&columns);
+        let reparsed: Vec<SemanticColumn> =
+            GridReader::new(std::io::BufReader::new(rendered.as_bytes()))
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(reparsed, columns);
+
+        let columns = vec![
+            SemanticColumn {
+                nums: vec![7, 123, 45],
+                op: Op::Add,
+            },
+            SemanticColumn {
+                nums: vec![0],
+                op: Op::Sub,
+            },
+        ];
+        let rendered = super::render(&columns);
+        assert_eq!(rendered, " 1\n 24\n735 0\n+   -");
+        let reparsed: Vec<SemanticColumn> =
+            GridReader::new(std::io::BufReader::new(rendered.as_bytes()))
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(reparsed, columns);
+        assert_eq!(super::render(&[]), "");
+    }
+
+    #[test]
+    fn test_strict_grid_reader() {
+        use super::{GridReader, ParseNumsOrOpsError};
+        const STRAY_INPUT: &str = "123 3x8\n 45 64\n*   +";
+
+        let reader = GridReader::new(std::io::BufReader::new(STRAY_INPUT.as_bytes()));
+        let result: Vec<usize> = reader.map(|col| col.unwrap().nums.len()).collect();
+        assert_eq!(result, vec![3, 3]);
+
+        let mut reader =
+            GridReader::new(std::io::BufReader::new(STRAY_INPUT.as_bytes())).with_strict(true);
+        assert_eq!(reader.next().unwrap().unwrap().nums, vec![1, 24, 35]);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(ParseNumsOrOpsError::ParseUnexpected {
+                row: 0,
+                col: 5,
+                byte: b'x'
+            }))
+        ));
+
+        // The row is the byte's line in the input, counting blank and separator lines.
+        let mut reader = GridReader::new(std::io::BufReader::new(
+            "\n12 34\n---\n5x 67\n\n+  *".as_bytes(),
+        ))
+        .with_strict(true);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(ParseNumsOrOpsError::ParseUnexpected {
+                row: 3,
+                col: 1,
+                byte: b'x'
+            }))
+        ));
+        let mut reader = GridReader::new(std::io::BufReader::new(
+            "\n12 34\n---\n5x 67\n\n+  *".as_bytes(),
+        ))
+        .with_strict(true)
+        .with_horizontal(true);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(ParseNumsOrOpsError::ParseUnexpected {
+                row: 3,
+                col: 1,
+                byte: b'x'
+            }))
+        ));
+
+        // Whitespace, separators, and exponent markers are all accepted, though the latter two
+        // are ignored.
+        let mut reader =
+            GridReader::new(std::io::BufReader::new(EXAMPLE_INPUT.as_bytes())).with_strict(true);
+        assert!(reader.all(|col| col.is_ok()));
+        let reader = GridReader::new(std::io::BufReader::new("1_\ne\t\n2 \n+ ".as_bytes()))
+            .with_strict(true);
+        assert_eq!(
+            reader.map(|col| col.unwrap().nums).collect::<Vec<_>>(),
+            vec![vec![12]]
+        );
+    }
+
+    #[test]
+    fn test_concat_op() {
+        let test_input = std::io::BufReader::new("12 5 0\n34 6 7\n|  + |".as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::vertical_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![1234, 11, 7]));
+
+        // A last row of pipes is still an operator row rather than a ruler.
+        let test_input = std::io::BufReader::new("12 5\n34 6\n|  |".as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::vertical_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![1234, 56]));
+
+        let test_input = std::io::BufReader::new("12 5\n34 60\n|  |".as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::columnar_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![1324, 560]));
+
+        use super::{ComputeError, Op, SemanticColumn};
+        let column = |nums| SemanticColumn {
+            nums,
+            op: Op::Concat,
+        };
+        assert_eq!(column(vec![10, 0, 5]).compute(Default::default()), Ok(1005));
+        assert_eq!(
+            column(vec![usize::MAX, 1]).compute(Default::default()),
+            Err(ComputeError::Overflow)
+        );
+        assert_eq!(
+            column(vec![1; 21]).compute(Default::default()),
+            Err(ComputeError::Overflow)
+        );
+    }
+
+    const INLINE_INPUT: &str = "
+123* 328+  51* 64+
+45*  64   387 23+
+6*   98+  215 314+";
+
+    #[test]
+    fn test_inline_math() {
+        let test_input = std::io::BufReader::new(INLINE_INPUT.as_bytes());
+        let result: Result<Vec<usize>, _> = super::inline_math(test_input, Default::default())
+            .unwrap()
+            .collect();
+        assert_eq!(result, Ok(vec![33210, 490, 4243455, 401]));
+
+        let test_input = std::io::BufReader::new("10- 4#\n3 5#\n".as_bytes());
+        let result: Result<Vec<usize>, _> = super::inline_math(test_input, Default::default())
+            .unwrap()
+            .collect();
+        assert_eq!(result, Ok(vec![7, 2]));
+    }
+
+    #[test]
+    fn test_inline_math_errors() {
+        use super::{ParseNumsOrOpsError, inline_math};
+        let test_input = std::io::BufReader::new("123+ 4+\n45* 5+".as_bytes());
+        assert!(matches!(
+            inline_math(test_input, Default::default()),
+            Err(ParseNumsOrOpsError::ParseConflictingOps { column: 0 })
+        ));
+        let test_input = std::io::BufReader::new("123+ 4\n45+ 5".as_bytes());
+        assert!(matches!(
+            inline_math(test_input, Default::default()),
+            Err(ParseNumsOrOpsError::ParseMissingOp { column: 1 })
+        ));
+        let test_input = std::io::BufReader::new("123+ +".as_bytes());
+        assert!(matches!(
+            inline_math(test_input, Default::default()),
+            Err(ParseNumsOrOpsError::ParseNum(_))
+        ));
+    }
+
+    #[test]
+    fn test_overflow_risk() {
+        use super::{ComputeError, Op, SemanticColumn};
+        let big = 1_usize << 32;
+        let column = |nums, op| SemanticColumn { nums, op };
+        assert!(column(vec![big, big], Op::Mul).overflow_risk());
+        assert!(!column(vec![big / 2, big - 1], Op::Mul).overflow_risk());
+        // The running product overflows before the zero is reached.
+        assert!(column(vec![big, big, 0], Op::Mul).overflow_risk());
+        assert!(column(vec![usize::MAX, 1], Op::Add).overflow_risk());
+        assert!(!column(vec![usize::MAX, 0], Op::Add).overflow_risk());
+        assert!(!column(vec![big, big], Op::Sub).overflow_risk());
+        assert!(!column(vec![], Op::Mul).overflow_risk());
+        // Even a column which overflows a u128 is only reported, never computed.
+        assert!(column(vec![usize::MAX; 4], Op::Mul).overflow_risk());
+        assert_eq!(
+            column(vec![big, big], Op::Mul).compute(Default::default()),
+            Err(ComputeError::Overflow)
+        );
+        assert_eq!(
+            column(vec![usize::MAX, 1], Op::Add).compute(Default::default()),
+            Err(ComputeError::Overflow)
+        );
+        assert_eq!(
+            column(vec![usize::MAX, 0], Op::Add).compute(Default::default()),
+            Ok(usize::MAX)
+        );
+
+        let grid = format!("{big} 5\n{big} 6\n*  *");
+        let test_input = std::io::BufReader::new(grid.as_bytes());
+        let risky: Vec<bool> = super::vertical_columns(test_input)
+            .map(|sem_col| sem_col.overflow_risk())
+            .collect();
+        assert_eq!(risky, vec![true, false]);
+        let test_input = std::io::BufReader::new(grid.as_bytes());
+        let result: Vec<_> = super::vertical_math(test_input, Default::default()).collect();
+        assert_eq!(result, vec![Err(ComputeError::Overflow), Ok(30)]);
+    }
+
+    #[test]
+    fn test_column_stats() {
+        use super::{Op, SemanticColumn};
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let stats: Vec<_> = super::vertical_columns(test_input)
+            .map(|sem_col| sem_col.column_stats())
+            .collect();
+        assert_eq!(stats[0], (6, 123, 174, 33210));
+        assert_eq!(stats[1], (64, 328, 490, 2_057_216));
+
+        let column = |nums, op| SemanticColumn { nums, op };
+        assert_eq!(column(vec![], Op::Add).column_stats(), (0, 0, 0, 0));
+        let max = usize::MAX;
+        assert_eq!(
+            column(vec![max, max], Op::Sub).column_stats(),
+            (max, max, max, max as u128 * max as u128)
+        );
+        assert_eq!(
+            column(vec![max, max, 2], Op::Add).column_stats(),
+            (2, max, max, u128::MAX)
+        );
+    }
+
+    #[test]
+    fn test_count_op() {
+        let test_input = std::io::BufReader::new("1 2 3\n4 5 6\n# + #".as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::vertical_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![2, 7, 2]));
+
+        let test_input = std::io::BufReader::new("12 3\n4  5\n#  +".as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::columnar_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![2, 35]));
+    }
+
+    #[test]
+    fn test_describe_grid() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let result = super::describe_grid(test_input);
+        assert_eq!(
+            result,
+            Ok(super::GridShape {
+                num_columns: 4,
+                num_number_rows: 3,
+                ops: vec![
+                    super::Op::Mul,
+                    super::Op::Add,
+                    super::Op::Mul,
+                    super::Op::Add
+                ],
+            })
+        );
+
+        // A column without an operator is reported rather than left out of the count.
+        let test_input = std::io::BufReader::new(SHORT_OPS_INPUT.as_bytes());
+        assert_eq!(
+            super::describe_grid(test_input),
+            Err(super::ParseNumsOrOpsError::ParseMissingOp { column: 4 })
+        );
+    }
+
+    #[test]
+    fn test_write_per_column() {
+        let mut output: Vec<u8> = Vec::new();
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let results = super::vertical_math(test_input, Default::default());
+        assert_eq!(super::write_per_column(results, &mut output), Ok(()));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "33210\n490\n4243455\n401\n"
+        );
+
+        let mut output: Vec<u8> = Vec::new();
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let results = super::columnar_math(test_input, Default::default());
+        assert_eq!(super::write_per_column(results, &mut output), Ok(()));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "8544\n625\n3253600\n1058\n"
+        );
+    }
+
+    #[test]
+    fn test_write_per_column_stops_at_error() {
+        let mut output: Vec<u8> = Vec::new();
+        let test_input = std::io::BufReader::new("20 5 7\n3 10 1\n- - +".as_bytes());
+        let results = super::vertical_math(test_input, Default::default());
+        assert_eq!(
+            super::write_per_column(results, &mut output),
+            Err(super::ComputeError::Underflow)
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), "17\n");
+    }
+
+    #[test]
+    fn test_grand_total() {
+        let test_input = std::io::BufReader::new(EXAMPLE_INPUT.as_bytes());
+        let result = super::grand_total(super::vertical_math(test_input, Default::default()));
+        assert_eq!(result, Ok(33210 + 490 + 4243455 + 401));
+    }
+
+    #[test]
+    fn test_grand_total_large_columns() {
+        let big = usize::MAX / 2 + 1;
+        assert!(big.checked_add(big).is_none());
+        let large_input = format!("{big} {big} {big}\n0 0 0\n+ + +\n");
+        let test_input = std::io::BufReader::new(large_input.as_bytes());
+        let result = super::grand_total(super::vertical_math(test_input, Default::default()));
+        assert_eq!(result, Ok(3 * big as u128));
+    }
+
+    const UNDERFLOW_INPUT: &str = "
+ 5 20
+10  3
+ -  -";
+
+    #[test]
+    fn test_sub_modes() {
+        use super::{ComputeError, ComputeOptions, Op, SemanticColumn, SubMode};
+        let column = SemanticColumn {
+            nums: vec![5, 10],
+            op: Op::Sub,
+        };
+        let with_mode = |sub_mode| ComputeOptions {
+            sub_mode,
+            ..Default::default()
+        };
+        assert_eq!(column.compute(with_mode(SubMode::Saturating)), Ok(0));
+        assert_eq!(
+            column.compute(with_mode(SubMode::Wrapping)),
+            Ok(usize::MAX - 4)
+        );
+        assert_eq!(
+            column.compute(with_mode(SubMode::Checked)),
+            Err(ComputeError::Underflow)
+        );
+        for (mode, expected) in [
+            (SubMode::Saturating, Ok(vec![0, 17])),
+            (SubMode::Wrapping, Ok(vec![usize::MAX - 4, 17])),
+            (SubMode::Checked, Err(ComputeError::Underflow)),
+        ] {
+            let test_input = std::io::BufReader::new(UNDERFLOW_INPUT.as_bytes());
+            let result: Result<Vec<usize>, _> =
+                super::vertical_math(test_input, with_mode(mode)).collect();
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn test_div_modes() {
+        use super::{ComputeError, ComputeOptions, DivMode, Op, SemanticColumn};
+        let with_mode = |div_mode| ComputeOptions {
+            div_mode,
+            ..Default::default()
+        };
+        let column = |nums| SemanticColumn { nums, op: Op::Div };
+        for (nums, expected) in [
+            (vec![12, 4], [Ok(3), Ok(3), Ok(3), Ok(3)]),
+            (
+                vec![7, 2],
+                [Err(ComputeError::Remainder), Ok(3), Ok(4), Ok(4)],
+            ),
+            (
+                vec![7, 3],
+                [Err(ComputeError::Remainder), Ok(2), Ok(3), Ok(2)],
+            ),
+            (vec![100, 5, 2], [Ok(10), Ok(10), Ok(10), Ok(10)]),
+            (vec![5, 0], [Err(ComputeError::DivideByZero); 4]),
+        ] {
+            let column = column(nums);
+            let result = [
+                DivMode::Exact,
+                DivMode::Floor,
+                DivMode::Ceil,
+                DivMode::Round,
+            ]
+            .map(|mode| column.compute(with_mode(mode)));
+            assert_eq!(result, expected);
+        }
+
+        let test_input = std::io::BufReader::new("12 7\n 4 2\n /  /".as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::vertical_math(test_input, with_mode(DivMode::Ceil)).collect();
+        assert_eq!(result, Ok(vec![3, 4]));
+    }
+
+    #[test]
+    fn test_parse_number() {
+        use super::{ParseNumsOrOpsError, parse_number};
+        assert!(matches!(parse_number("12"), Ok(12)));
+        assert!(matches!(parse_number("1_000"), Ok(1000)));
+        assert!(matches!(parse_number("1e3"), Ok(1000)));
+        assert!(matches!(parse_number("1_0e2"), Ok(1000)));
+        assert!(matches!(parse_number("1.5e3"), Ok(1500)));
+        assert!(matches!(parse_number("1.50e1"), Ok(15)));
+        assert!(matches!(
+            parse_number("1.5e0"),
+            Err(ParseNumsOrOpsError::ParseNotIntegral)
+        ));
+        assert!(matches!(
+            parse_number("2e30"),
+            Err(ParseNumsOrOpsError::ParseOverflow)
+        ));
+        assert!(matches!(
+            parse_number("1e"),
+            Err(ParseNumsOrOpsError::ParseNum(_))
+        ));
+    }
+
+    #[test]
+    fn test_readable_numbers() {
+        let test_input = std::io::BufReader::new("1_000 1e3\n    2   3\n    + *".as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::vertical_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![1002, 3000]));
+
+        // A column is never read as scientific notation, so its `e` is ignored like its `_`.
+        let test_input = std::io::BufReader::new("1 4\ne 2\n2 _\n+ +".as_bytes());
+        let result: Result<Vec<usize>, _> =
+            super::columnar_math(test_input, Default::default()).collect();
+        assert_eq!(result, Ok(vec![12, 42]));
+    }
+
+    /// Returns the next xorshift value of `seed`, reduced into `[low, high)`.
+    fn random_in(seed: &mut u64, low: usize, high: usize) -> usize {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        low + (*seed % (high - low) as u64) as usize
+    }
+
+    /// The columnar reading of a grid of single digits, where `digits[j]` holds the digits of
+    /// column `j` from top to bottom. Each character column is a single number, written out and
+    /// parsed here, so every operator sees just that one number and `#` counts it once.
+    fn reference_columnar(digits: &[Vec<usize>], ops: &[super::Op]) -> Vec<usize> {
+        digits
+            .iter()
+            .zip(ops)
+            .map(|(column, op)| match op {
+                super::Op::Count => 1,
+                _ => column
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<String>()
+                    .parse()
+                    .unwrap(),
+            })
+            .collect()
+    }
+
+    /// Generates grids where every cell is a single digit and columns are separated by a single
+    /// space, and compares the two readings.
+    ///
+    /// The columnar reading treats each character column as one number formed from all of its
+    /// digits, while the vertical reading reduces each digit as its own number. The two agree on
+    /// a column exactly when reducing its digits gives the same value as reading them as one
+    /// number. That always holds for `|` and for a single row, and can hold by coincidence for
+    /// other operators, such as `+` over 0 and 5, but in general they disagree, such as `+` over
+    /// 1 and 2 giving 3 rather than 12.
+    #[test]
+    fn test_columnar_vs_vertical() {
+        let readings = |grid: &str| {
+            let columnar: Vec<_> =
+                super::columnar_math(std::io::BufReader::new(grid.as_bytes()), Default::default())
+                    .collect();
+            let vertical: Vec<_> =
+                super::vertical_math(std::io::BufReader::new(grid.as_bytes()), Default::default())
+                    .map(|result| result.map_err(super::ColumnarError::from))
+                    .collect();
+            (columnar, vertical)
+        };
+        assert_eq!(readings("0\n5\n+"), (vec![Ok(5)], vec![Ok(5)]));
+        assert_eq!(readings("0\n0\n*"), (vec![Ok(0)], vec![Ok(0)]));
+        assert_eq!(readings("1\n2\n+"), (vec![Ok(12)], vec![Ok(3)]));
+
+        use super::Op;
+        let mut seed: u64 = 0x2025_1206;
+        for _ in 0..1000 {
+            let num_rows = random_in(&mut seed, 1, 6);
+            let num_columns = random_in(&mut seed, 1, 8);
+            let digits: Vec<Vec<usize>> = (0..num_columns)
+                .map(|_| (0..num_rows).map(|_| random_in(&mut seed, 0, 10)).collect())
+                .collect();
+            let ops: Vec<Op> = (0..num_columns)
+                .map(|_| {
+                    let symbol = random_in(&mut seed, 0, 6);
+                    "+*-/#|"[symbol..=symbol].parse().unwrap()
+                })
+                .collect();
+            let mut grid = String::new();
+            for row in 0..num_rows {
+                let cells: Vec<String> = digits.iter().map(|col| col[row].to_string()).collect();
+                grid.push_str(&cells.join(" "));
+                grid.push('\n');
+            }
+            let op_cells: Vec<String> = ops.iter().map(Op::to_string).collect();
+            grid.push_str(&op_cells.join(" "));
+
+            let (columnar, vertical) = readings(&grid);
+            let reference: Vec<_> = reference_columnar(&digits, &ops)
+                .into_iter()
+                .map(Ok)
+                .collect();
+            assert_eq!(columnar, reference, "{grid}");
+            if num_rows == 1 {
+                assert_eq!(columnar, vertical, "{grid}");
+            }
+            for ((col, vert), op) in columnar.iter().zip(&vertical).zip(&ops) {
+                if *op == Op::Concat {
+                    assert_eq!(col, vert, "{grid}");
+                }
+            }
+        }
     }
 }